@@ -0,0 +1,117 @@
+//! # Brute-Force Reference Solver
+//!
+//! Exhaustively enumerates all assignments of a (tiny) instance to compute the
+//! exact Pareto front. This is intended as a ground-truth oracle.
+
+use rustsat::{
+    instances::{ManageVars, MultiOptInstance},
+    types::{Assignment, Lit},
+};
+use scuttle::types::{NonDomPoint, ParetoFront};
+
+use crate::{
+    config::BruteForceConfig,
+    eval::{self, Relation},
+    Solver,
+};
+
+/// The maximum number of variables that can be enumerated
+const MAX_VARS: u32 = 63;
+
+pub struct BruteForce {
+    inst: MultiOptInstance,
+    conf: BruteForceConfig,
+}
+
+impl BruteForce {
+    pub fn new(inst: MultiOptInstance, conf: BruteForceConfig) -> Self {
+        BruteForce { inst, conf }
+    }
+}
+
+impl From<MultiOptInstance> for BruteForce {
+    fn from(value: MultiOptInstance) -> Self {
+        BruteForce::new(value, BruteForceConfig::default())
+    }
+}
+
+impl Solver for BruteForce {
+    fn run(&mut self) -> Result<ParetoFront, String> {
+        let n_vars = n_vars(&self.inst);
+        if n_vars > MAX_VARS {
+            return Err(format!(
+                "too many variables for brute-force solving ({} > {})",
+                n_vars, MAX_VARS
+            ));
+        }
+        let mut non_dom: Vec<(Vec<isize>, Vec<Assignment>)> = vec![];
+        'assignments: for bits in 0..1u64 << n_vars {
+            let mut sol = Assignment::default();
+            for idx in 0..n_vars {
+                sol.assign_lit(Lit::new(idx, bits >> idx & 1 == 0));
+            }
+            let Some(cost) = self.inst.cost(&sol) else {
+                continue;
+            };
+            let mut idx = 0;
+            while idx < non_dom.len() {
+                match eval::check_relation(&cost, &non_dom[idx].0) {
+                    Relation::Incomparable => idx += 1,
+                    Relation::FirstDominates => {
+                        non_dom.swap_remove(idx);
+                    }
+                    Relation::SecondDominates => continue 'assignments,
+                    Relation::Equal => {
+                        if self.conf.enumerate {
+                            non_dom[idx].1.push(sol);
+                        }
+                        continue 'assignments;
+                    }
+                }
+            }
+            non_dom.push((cost, vec![sol]));
+        }
+        let mut pf = ParetoFront::default();
+        for (cost, sols) in non_dom {
+            let mut ndom = NonDomPoint::new(cost);
+            sols.into_iter().for_each(|sol| ndom.add_sol(sol));
+            pf.add_nd(ndom);
+        }
+        Ok(pf)
+    }
+}
+
+/// The number of variables in an instance
+pub fn n_vars(inst: &MultiOptInstance) -> u32 {
+    let (_, _, var_manager) = inst.clone().as_hard_cls_soft_cls();
+    var_manager.n_used()
+}
+
+#[cfg(test)]
+mod tests {
+    use rustsat::{
+        clause,
+        instances::{MultiOptInstance, Objective, SatInstance},
+        lit,
+    };
+
+    use crate::Solver;
+
+    use super::BruteForce;
+
+    #[test]
+    fn two_objectives() {
+        let mut constr = SatInstance::default();
+        constr.add_clause(clause![lit![0], lit![1]]);
+        let mut obj1 = Objective::default();
+        obj1.add_soft_clause(1, clause![!lit![0]]);
+        let mut obj2 = Objective::default();
+        obj2.add_soft_clause(1, clause![!lit![1]]);
+        let inst = MultiOptInstance::compose(constr, vec![obj1, obj2]);
+        let pf = BruteForce::from(inst).run().unwrap();
+        assert_eq!(pf.len(), 2);
+        let mut costs: Vec<_> = pf.iter().map(|nd| nd.costs().clone()).collect();
+        costs.sort();
+        assert_eq!(costs, vec![vec![0, 1], vec![1, 0]]);
+    }
+}
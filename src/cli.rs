@@ -1,18 +1,24 @@
 //! # Command Line Interface
 
-use std::{fmt, fs, io::Write, path::PathBuf};
+use std::{cmp::Reverse, fmt, fs, io::Write, path::PathBuf};
 
 use clap::{Args, Parser, Subcommand, ValueEnum};
 use itertools::Itertools;
 use prettytable::{self, row, table, Table};
 use rustsat::{
     instances::{fio::opb, MultiOptInstance},
-    types::RsHashSet,
+    types::{RsHashMap, RsHashSet},
 };
 use termcolor::{BufferWriter, Color, ColorSpec, WriteColor};
 
 use crate::{
-    config::{Config, EvalConfig, FuzzConfig, InstConfig},
+    config::{
+        Config, EvalConfig, FuzzConfig, InstConfig, InstFormat, MinimizeConfig, ScuttleOptions,
+        SolverConfig, SuppressionFile,
+    },
+    fuzz::{Bug, Signature},
+    gen::Features,
+    min::{self, TargetFilter},
     Problem,
 };
 
@@ -32,6 +38,9 @@ enum Command {
     /// Generate a random instance
     #[clap(visible_alias = "gen")]
     Generate {
+        /// The file format to write the instance in
+        #[arg(long, value_enum, default_value_t = InstFormat::Mcnf)]
+        output_format: InstFormat,
         #[command(flatten)]
         inst: InstGenArgs,
         #[command(flatten)]
@@ -40,6 +49,21 @@ enum Command {
     /// Minimize a faulty instance
     #[clap(visible_alias = "min")]
     Minimize {
+        /// The number of worker threads
+        #[arg(short = 'j', long)]
+        workers: Option<u8>,
+        /// The path to write the minimized instance to. Defaults to the input
+        /// path with a `.min.mcnf` extension.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Only minimize for a problem of this solver. Defaults to the first
+        /// solver with a problem by name.
+        #[arg(long)]
+        target_solver: Option<String>,
+        /// Only minimize for a problem of this kind, e.g., `panic` or
+        /// `"dominated by other"`
+        #[arg(long)]
+        target_problem: Option<String>,
         #[command(flatten)]
         solvers: SolverArgs,
         #[command(flatten)]
@@ -68,10 +92,31 @@ enum Command {
         #[command(flatten)]
         inst: InstGenArgs,
         #[command(flatten)]
+        limits: FuzzLimitArgs,
+        /// A `.toml` file listing known bugs to suppress
+        #[arg(long)]
+        suppressions: Option<PathBuf>,
+        /// The directory to write found bugs to, with one subdirectory per
+        /// bug
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// The file format to write found instances in
+        #[arg(long, value_enum)]
+        output_format: Option<InstFormat>,
+        #[command(flatten)]
         solvers: SolverArgs,
         #[command(flatten)]
         config: ConfigArgs,
     },
+    /// Runs a single solver as an isolated worker process
+    #[clap(hide = true)]
+    Worker {
+        /// The serialized solver configuration
+        #[arg(long)]
+        solver: String,
+        /// The path to the MCNF instance file to solve
+        instance: PathBuf,
+    },
 }
 
 #[derive(Args)]
@@ -88,6 +133,30 @@ struct InstGenArgs {
     /// The maximum number of layers in the generated instance(s)
     #[arg(long)]
     max_layers: Option<u8>,
+    /// Randomly switch generator features on or off per instance (swarm
+    /// testing)
+    #[arg(long)]
+    swarm: bool,
+    /// Plant a random assignment that satisfies all hard constraints, to
+    /// guarantee satisfiable instances
+    #[arg(long)]
+    planted: bool,
+}
+
+#[derive(Args)]
+struct FuzzLimitArgs {
+    /// The number of instances to test
+    #[arg(short = 'n', long)]
+    instances: Option<usize>,
+    /// The wall-clock time budget in seconds
+    #[arg(long)]
+    time_budget: Option<f64>,
+    /// Stop after this many unique bugs have been found
+    #[arg(long)]
+    max_bugs: Option<usize>,
+    /// Keep fuzzing until interrupted with Ctrl-C
+    #[arg(long)]
+    until_interrupted: bool,
 }
 
 #[derive(Args)]
@@ -108,8 +177,8 @@ struct ConfigArgs {
 #[derive(Args)]
 struct InstArgs {
     /// The file format of the input file. With infer, the file format is
-    /// inferred from the file extension. Note that generated files will always
-    /// be in MCNF format.
+    /// inferred from the file extension. Note that the minimize command always
+    /// writes the minimized instance in MCNF format.
     #[arg(long, value_enum, default_value_t = FileFormat::Infer)]
     file_format: FileFormat,
     /// The index in the OPB file to treat as the lowest variable
@@ -150,9 +219,17 @@ pub struct Cli {
 }
 
 pub enum Exec {
-    Generate(InstConfig),
+    Generate(InstConfig, InstFormat),
     Fuzz(FuzzConfig),
     Evaluate(EvalConfig, MultiOptInstance),
+    Minimize(
+        EvalConfig,
+        MinimizeConfig,
+        TargetFilter,
+        MultiOptInstance,
+        PathBuf,
+    ),
+    Worker(SolverConfig, MultiOptInstance),
 }
 
 #[macro_export]
@@ -209,11 +286,20 @@ impl Cli {
                 }
             }),
         };
+        if let Command::Worker { solver, instance } = &args.command {
+            let config: SolverConfig = toml::from_str(solver).unwrap_or_else(panic_with_err!(cli));
+            let inst = MultiOptInstance::from_dimacs_path(instance.clone())
+                .unwrap_or_else(panic_with_err!(cli));
+            return (cli, Exec::Worker(config, inst));
+        }
         let mut config = {
             let (Command::Generate { config, .. }
             | Command::Fuzz { config, .. }
             | Command::Minimize { config, .. }
-            | Command::Evaluate { config, .. }) = &args.command;
+            | Command::Evaluate { config, .. }) = &args.command
+            else {
+                unreachable!()
+            };
 
             cli.info(&format!("loading config from {:?}", config.config_path));
             let mut config: Config = toml::from_str(
@@ -236,6 +322,12 @@ impl Cli {
                     if let Some(val) = inst.max_layers {
                         inst_config.set_max_layers(val);
                     }
+                    if inst.swarm {
+                        inst_config.swarm = true;
+                    }
+                    if inst.planted {
+                        inst_config.planted = true;
+                    }
                 }
             }
 
@@ -304,14 +396,44 @@ impl Cli {
             None
         };
         let exec = match args.command {
-            Command::Generate { .. } => {
+            Command::Generate { output_format, .. } => {
                 let mut config: InstConfig = config.try_into().unwrap_or_else(panic_with_err!(cli));
                 if let Some(val) = args.seed {
                     config.seed = Some(val);
                 }
-                Exec::Generate(config)
+                Exec::Generate(config, output_format)
+            }
+            Command::Minimize {
+                workers,
+                output,
+                target_solver,
+                target_problem,
+                inst: inst_args,
+                ..
+            } => {
+                if let Some(val) = workers {
+                    match &mut config.execution {
+                        Some(exec) => exec.n_workers = val,
+                        None => panic!("missing execution block in config"),
+                    }
+                }
+                let mut min_config: MinimizeConfig = config
+                    .minimization
+                    .take()
+                    .ok_or("missing minimization block in config")
+                    .unwrap_or_else(panic_with_err!(cli));
+                if let Some(val) = args.seed {
+                    min_config.seed = Some(val);
+                }
+                let config: EvalConfig = config.try_into().unwrap_or_else(panic_with_err!(cli));
+                let output =
+                    output.unwrap_or_else(|| inst_args.instance.with_extension("min.mcnf"));
+                let filter = TargetFilter {
+                    solver: target_solver,
+                    kind: target_problem,
+                };
+                Exec::Minimize(config, min_config, filter, inst.unwrap(), output)
             }
-            Command::Minimize { .. } => todo!(),
             Command::Evaluate { workers, .. } => {
                 if let Some(val) = workers {
                     match &mut config.execution {
@@ -323,16 +445,50 @@ impl Cli {
                 let inst = inst.unwrap();
                 Exec::Evaluate(config, inst)
             }
-            Command::Fuzz { workers, .. } => {
+            Command::Fuzz {
+                workers,
+                limits,
+                suppressions,
+                output,
+                output_format,
+                ..
+            } => {
                 if let Some(val) = workers {
                     match &mut config.execution {
                         Some(exec) => exec.n_workers = val,
                         None => panic!("missing execution block in config"),
                     }
                 }
-                let config: FuzzConfig = config.try_into().unwrap_or_else(panic_with_err!(cli));
+                let mut config: FuzzConfig = config.try_into().unwrap_or_else(panic_with_err!(cli));
+                if let Some(val) = limits.instances {
+                    config.options.instances = Some(val);
+                }
+                if let Some(val) = limits.time_budget {
+                    config.options.time_budget = Some(val);
+                }
+                if let Some(val) = limits.max_bugs {
+                    config.options.max_bugs = Some(val);
+                }
+                if limits.until_interrupted {
+                    config.options.until_interrupted = true;
+                }
+                if let Some(val) = output {
+                    config.options.output = Some(val);
+                }
+                if let Some(val) = output_format {
+                    config.options.output_format = val;
+                }
+                if let Some(path) = suppressions.or(config.options.suppressions.clone()) {
+                    cli.info(&format!("loading suppressions from {:?}", path));
+                    let file: SuppressionFile = toml::from_str(
+                        &fs::read_to_string(path).unwrap_or_else(panic_with_err!(cli)),
+                    )
+                    .unwrap_or_else(panic_with_err!(cli));
+                    config.suppressions = file.suppress;
+                }
                 Exec::Fuzz(config)
             }
+            Command::Worker { .. } => unreachable!(),
         };
         (cli, exec)
     }
@@ -392,6 +548,32 @@ impl Cli {
         table.print_tty(self.color).expect("cannot write table");
     }
 
+    pub fn print_bugs<'a, Iter>(&self, iter: Iter)
+    where
+        Iter: IntoIterator<Item = (&'a Signature, &'a Bug)>,
+    {
+        let mut table = Table::new();
+        for (sig, bug) in iter
+            .into_iter()
+            .sorted_by_key(|(_, bug)| Reverse(bug.occurrences))
+        {
+            table.add_row(row![sig, bug.occurrences, bug.seed, bug.size]);
+        }
+        if self.color {
+            table.set_titles(
+                row![bFc->"Bug (solver: problem)", bFc->"# Occurrences", bFc->"Smallest Instance Seed", bFc->"Size"],
+            );
+        } else {
+            table.set_titles(row![
+                "Bug (solver: problem)",
+                "# Occurrences",
+                "Smallest Instance Seed",
+                "Size"
+            ]);
+        }
+        table.print_tty(self.color).expect("cannot write table");
+    }
+
     pub fn print_instance_problems<'a, Iter>(&self, iter: Iter)
     where
         Iter: IntoIterator<Item = (&'a u64, &'a Vec<(String, Problem)>)>,
@@ -421,29 +603,103 @@ impl Cli {
         table.print_tty(self.color).expect("cannot write table");
     }
 
-    pub fn print_solver_problems<'a, Iter>(&self, iter: Iter)
+    pub fn print_sampled_options<'a, Iter>(&self, iter: Iter)
+    where
+        Iter: IntoIterator<Item = (&'a u64, &'a Vec<(String, ScuttleOptions)>)>,
+    {
+        let mut table = Table::new();
+        for (seed, sampled) in iter {
+            for (slv, opts) in sampled {
+                table.add_row(row![
+                    seed,
+                    slv,
+                    toml::to_string(opts).expect("failed to serialize options")
+                ]);
+            }
+        }
+        if table.is_empty() {
+            return;
+        }
+        if self.color {
+            table.set_titles(row![bFc->"Instance Seed", bFc->"Solver", bFc->"Sampled Options"]);
+        } else {
+            table.set_titles(row!["Instance Seed", "Solver", "Sampled Options"]);
+        }
+        table.print_tty(self.color).expect("cannot write table");
+    }
+
+    pub fn print_features<'a, Iter>(&self, iter: Iter)
+    where
+        Iter: IntoIterator<Item = (&'a u64, &'a Features)>,
+    {
+        let mut table = Table::new();
+        for (seed, features) in iter {
+            table.add_row(row![seed, features]);
+        }
+        if self.color {
+            table.set_titles(row![bFc->"Instance Seed", bFc->"Generator Features"]);
+        } else {
+            table.set_titles(row!["Instance Seed", "Generator Features"]);
+        }
+        table.print_tty(self.color).expect("cannot write table");
+    }
+
+    pub fn print_minimized<'a, Iter>(&self, iter: Iter)
+    where
+        Iter: IntoIterator<Item = (&'a u64, &'a (min::Size, Option<min::Size>))>,
+    {
+        let mut table = Table::new();
+        for (seed, (orig, red)) in iter {
+            match red {
+                Some(red) => table.add_row(row![seed, orig, red]),
+                None => table.add_row(row![seed, orig, "not minimized"]),
+            };
+        }
+        if self.color {
+            table.set_titles(
+                row![bFc->"Instance Seed", bFc->"Original Size", bFc->"Minimized Size"],
+            );
+        } else {
+            table.set_titles(row!["Instance Seed", "Original Size", "Minimized Size"]);
+        }
+        table.print_tty(self.color).expect("cannot write table");
+    }
+
+    pub fn print_solver_problems<'a, Iter, SkipIter>(&self, iter: Iter, skipped: SkipIter)
     where
         Iter: IntoIterator<Item = (&'a String, &'a Vec<(u64, Problem)>)>,
+        SkipIter: IntoIterator<Item = (&'a String, &'a usize)>,
     {
+        let mut skipped: RsHashMap<&String, usize> =
+            skipped.into_iter().map(|(slv, &cnt)| (slv, cnt)).collect();
         let mut table = Table::new();
         for (slv, probs) in iter {
             table.add_row(row![
                 slv,
                 probs.len(),
+                skipped.remove(slv).unwrap_or(0),
                 probs
                     .iter()
-                    .map(|(seed, prob)| format!("{}: {}", slv, prob))
+                    .map(|(seed, prob)| format!("{}: {}", seed, prob))
                     .format("\n")
             ]);
         }
+        // Solvers without problems that were skipped on some instances
+        for (slv, cnt) in skipped {
+            table.add_row(row![slv, 0, cnt, ""]);
+        }
         if self.color {
-            table.set_titles(
-                row![bFc->"Solver", bFc->"# Problems", bFc->"Problems (seed: problem)"],
-            );
+            table.set_titles(row![
+                bFc->"Solver",
+                bFc->"# Problems",
+                bFc->"# Skipped",
+                bFc->"Problems (seed: problem)"
+            ]);
         } else {
             table.set_titles(row![
-                "Instance Seed",
+                "Solver",
                 "# Problems",
+                "# Skipped",
                 "Problems (seed: problem)"
             ]);
         }
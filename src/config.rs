@@ -1,10 +1,13 @@
 //! # Fuzzer Configuration
 
-use std::ops::RangeInclusive;
+use std::{fmt, ops::RangeInclusive, path::PathBuf, time::Duration};
 
+use clap::ValueEnum;
 use futures::executor::{ThreadPool, ThreadPoolBuilder};
 use rustsat::types::RsHashMap;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+
+use crate::{external, fuzz::BRUTE_FORCE_ORACLE, Problem};
 
 #[derive(Deserialize)]
 pub struct Config {
@@ -12,13 +15,20 @@ pub struct Config {
     pub instances: Option<InstConfig>,
     pub solvers: Option<RsHashMap<String, SolverConfig>>,
     pub minimization: Option<MinimizeConfig>,
+    pub checks: Option<CheckConfig>,
+    pub fuzzing: Option<FuzzOptions>,
 }
 
 pub struct FuzzConfig {
     pub pool: Option<ThreadPool>,
+    pub execution: ExecConfig,
     pub instances: InstConfig,
     pub solvers: RsHashMap<String, SolverConfig>,
     pub minimization: Option<MinimizeConfig>,
+    pub checks: CheckConfig,
+    pub options: FuzzOptions,
+    /// Known bugs that are counted but not reported
+    pub suppressions: Vec<Suppression>,
 }
 
 impl TryFrom<Config> for FuzzConfig {
@@ -37,11 +47,27 @@ impl TryFrom<Config> for FuzzConfig {
         if value.instances.is_none() {
             return Err("missing solvers block in config");
         }
+        let execution = value.execution.unwrap();
+        let instances = value.instances.unwrap();
+        instances.validate()?;
+        let mut solvers = value.solvers.unwrap();
+        check_solvers(&solvers)?;
+        let checks = value.checks.unwrap_or_default();
+        if checks.brute_force_max_vars.is_some() && solvers.contains_key(BRUTE_FORCE_ORACLE) {
+            return Err("solver name brute-force is reserved for the brute-force reference solver");
+        }
+        if execution.isolate {
+            solvers.values_mut().for_each(|s| s.isolate = true);
+        }
         Ok(FuzzConfig {
-            pool: value.execution.unwrap().into(),
-            instances: value.instances.unwrap(),
-            solvers: value.solvers.unwrap(),
+            pool: execution.into(),
+            execution,
+            instances,
+            solvers,
             minimization: value.minimization,
+            checks,
+            options: value.fuzzing.unwrap_or_default(),
+            suppressions: vec![],
         })
     }
 }
@@ -49,6 +75,7 @@ impl TryFrom<Config> for FuzzConfig {
 pub struct EvalConfig {
     pub pool: Option<ThreadPool>,
     pub solvers: RsHashMap<String, SolverConfig>,
+    pub checks: CheckConfig,
 }
 
 impl TryFrom<Config> for EvalConfig {
@@ -61,16 +88,67 @@ impl TryFrom<Config> for EvalConfig {
         if value.execution.is_none() {
             return Err("missing execution block in config");
         }
+        let execution = value.execution.unwrap();
+        let mut solvers = value.solvers.unwrap();
+        check_solvers(&solvers)?;
+        if execution.isolate {
+            solvers.values_mut().for_each(|s| s.isolate = true);
+        }
         Ok(EvalConfig {
-            pool: value.execution.unwrap().into(),
-            solvers: value.solvers.unwrap(),
+            pool: execution.into(),
+            solvers,
+            checks: value.checks.unwrap_or_default(),
         })
     }
 }
 
-#[derive(Deserialize)]
+/// Checks the solver configurations for settings that cannot be used
+fn check_solvers(solvers: &RsHashMap<String, SolverConfig>) -> Result<(), &'static str> {
+    if solvers.values().any(|s| !s.oracle.available()) {
+        return Err("SAT oracle not enabled in this build");
+    }
+    if solvers
+        .values()
+        .any(|s| s.options.is_some() && !matches!(s.solver, SolverKind::Scuttle(_)))
+    {
+        return Err("scuttle options are only supported for scuttle solvers");
+    }
+    for sconf in solvers.values() {
+        match (&sconf.solver, &sconf.options) {
+            (SolverKind::Scuttle(alg), Some(opts)) => {
+                if !alg.is_divcon() && opts.has_divcon_options() {
+                    return Err(
+                        "divide and conquer options are only supported for divide and conquer scuttle solvers",
+                    );
+                }
+                if !alg.takes_sub_problem_size() && opts.sub_problem_size.is_some() {
+                    return Err(
+                        "sub_problem_size is only supported for DivConPMinimal and DivConLowerBounding",
+                    );
+                }
+                if !alg.takes_n_minus() && opts.n_minus.is_some() {
+                    return Err("n_minus is only supported for DivConNMinus");
+                }
+            }
+            (SolverKind::External(ext), _) => match ext.command.first() {
+                None => return Err("empty external solver command"),
+                Some(program) if !external::is_executable(program) => {
+                    return Err("external solver command not found or not executable")
+                }
+                Some(_) => (),
+            },
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy)]
 pub struct ExecConfig {
     pub n_workers: u8,
+    /// Run all solvers in isolated worker processes
+    #[serde(default)]
+    pub isolate: bool,
 }
 
 impl From<ExecConfig> for Option<ThreadPool> {
@@ -94,9 +172,18 @@ pub struct InstConfig {
     layer_clauses: U8DivRange,
     equalities: U8ProbRange,
     ands: U8ProbRange,
-    xors3: U8ProbRange,
-    xors4: U8ProbRange,
+    #[serde(default)]
+    xors: Vec<XorConfig>,
+    #[serde(default)]
+    cards: Vec<CardConfig>,
+    pbs: Option<PbConfig>,
     max_weight: Vec<U64Range>,
+    /// Randomly switch generator features on or off per instance
+    #[serde(default)]
+    pub swarm: bool,
+    /// Plant a random assignment that satisfies all hard constraints
+    #[serde(default)]
+    pub planted: bool,
 }
 
 impl InstConfig {
@@ -130,17 +217,47 @@ impl InstConfig {
     pub fn ands_nonzero_prob(&self) -> f64 {
         1. - self.ands.zero_prob
     }
-    pub fn xors3_range(&self) -> RangeInclusive<u32> {
-        self.xors3.min.into()..=self.xors3.max.into()
+    pub fn xor_kinds(&self) -> usize {
+        self.xors.len()
     }
-    pub fn xors3_nonzero_prob(&self) -> f64 {
-        1. - self.xors3.zero_prob
+    pub fn xor_arity(&self, kind: usize) -> u32 {
+        self.xors[kind].arity.into()
     }
-    pub fn xors4_range(&self) -> RangeInclusive<u32> {
-        self.xors4.min.into()..=self.xors4.max.into()
+    pub fn xors_range(&self, kind: usize) -> RangeInclusive<u32> {
+        self.xors[kind].min.into()..=self.xors[kind].max.into()
     }
-    pub fn xors4_nonzero_prob(&self) -> f64 {
-        1. - self.xors4.zero_prob
+    pub fn xors_nonzero_prob(&self, kind: usize) -> f64 {
+        1. - self.xors[kind].zero_prob
+    }
+    pub fn card_kinds(&self) -> usize {
+        self.cards.len()
+    }
+    pub fn card_kind(&self, kind: usize) -> CardKind {
+        self.cards[kind].kind
+    }
+    pub fn cards_range(&self, kind: usize) -> RangeInclusive<u32> {
+        self.cards[kind].min.into()..=self.cards[kind].max.into()
+    }
+    pub fn cards_nonzero_prob(&self, kind: usize) -> f64 {
+        1. - self.cards[kind].zero_prob
+    }
+    pub fn pbs_range(&self) -> RangeInclusive<u32> {
+        match &self.pbs {
+            Some(pbs) => pbs.min.into()..=pbs.max.into(),
+            None => 0..=0,
+        }
+    }
+    pub fn pbs_nonzero_prob(&self) -> f64 {
+        match &self.pbs {
+            Some(pbs) => 1. - pbs.zero_prob,
+            None => 0.,
+        }
+    }
+    pub fn pb_coeffs(&self) -> RangeInclusive<usize> {
+        match &self.pbs {
+            Some(pbs) => 1..=pbs.max_coeff,
+            None => 1..=1,
+        }
     }
     pub fn max_weight_variants(&self) -> usize {
         self.max_weight.len()
@@ -160,6 +277,27 @@ impl InstConfig {
     pub fn set_min_layers(&mut self, min_layers: u8) {
         self.layers.min = min_layers
     }
+    /// Checks for values that the generator cannot handle
+    fn validate(&self) -> Result<(), &'static str> {
+        if self.xors.iter().any(|xor| xor.arity == 0) {
+            return Err("xor arity must be at least 1");
+        }
+        if self.xors.iter().any(|xor| xor.min > xor.max) {
+            return Err("xor range must have min <= max");
+        }
+        if self.cards.iter().any(|card| card.min > card.max) {
+            return Err("cardinality constraint range must have min <= max");
+        }
+        if let Some(pbs) = &self.pbs {
+            if pbs.min > pbs.max {
+                return Err("PB constraint range must have min <= max");
+            }
+            if pbs.max_coeff == 0 {
+                return Err("PB max_coeff must be at least 1");
+            }
+        }
+        Ok(())
+    }
 }
 
 impl TryFrom<Config> for InstConfig {
@@ -169,17 +307,25 @@ impl TryFrom<Config> for InstConfig {
         if value.instances.is_none() {
             return Err("missing instances block");
         }
-        Ok(value.instances.unwrap())
+        let config = value.instances.unwrap();
+        config.validate()?;
+        Ok(config)
     }
 }
 
 /// A range to draw random values from
-#[derive(Deserialize, Clone, Copy)]
+#[derive(Deserialize, Serialize, Clone, Copy)]
 pub struct U8Range {
     min: u8,
     max: u8,
 }
 
+impl U8Range {
+    pub fn contains(&self, val: usize) -> bool {
+        (self.min.into()..=self.max.into()).contains(&val)
+    }
+}
+
 /// A range to draw random values from
 #[derive(Deserialize, Clone, Copy)]
 pub struct U64Range {
@@ -211,8 +357,189 @@ pub struct U8DivRange {
     div: u8,
 }
 
+/// XOR constraints of a given arity
+#[derive(Deserialize, Clone, Copy)]
+pub struct XorConfig {
+    /// The number of literals in each constraint. Must be at least 1.
+    arity: u8,
+    zero_prob: f64,
+    min: u8,
+    max: u8,
+}
+
+/// Cardinality constraints of a given kind
+#[derive(Deserialize, Clone, Copy)]
+pub struct CardConfig {
+    kind: CardKind,
+    zero_prob: f64,
+    min: u8,
+    max: u8,
+}
+
+/// The kinds of generated cardinality constraints. They are displayed with
+/// the same names as in the config.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CardKind {
+    AtMost,
+    AtLeast,
+    Exactly,
+    ExactlyOne,
+}
+
+impl fmt::Display for CardKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CardKind::AtMost => write!(f, "AtMost"),
+            CardKind::AtLeast => write!(f, "AtLeast"),
+            CardKind::Exactly => write!(f, "Exactly"),
+            CardKind::ExactlyOne => write!(f, "ExactlyOne"),
+        }
+    }
+}
+
+/// Pseudo-Boolean constraints
+#[derive(Deserialize, Clone, Copy)]
+pub struct PbConfig {
+    zero_prob: f64,
+    min: u8,
+    max: u8,
+    /// The maximum coefficient of a literal
+    max_coeff: usize,
+}
+
+/// Additional checks of the returned Pareto fronts that do not rely on
+/// comparing solvers
+#[derive(Deserialize, Serialize, Clone, Copy)]
+pub struct CheckConfig {
+    /// Prove that every Pareto front is complete with an independent SAT solver
+    #[serde(default)]
+    pub completeness: bool,
+    /// Check that the hard clauses are unsatisfiable when a solver returns an
+    /// empty Pareto front
+    #[serde(default = "default_true")]
+    pub empty_fronts: bool,
+    /// Automatically add the brute-force solver as a reference for generated
+    /// instances with at most this many variables
+    pub brute_force_max_vars: Option<u32>,
+}
+
+impl Default for CheckConfig {
+    fn default() -> Self {
+        Self {
+            completeness: false,
+            empty_fronts: true,
+            brute_force_max_vars: None,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// The number of instances to fuzz if no other stop condition is given
+const DEFAULT_FUZZ_INSTANCES: usize = 5;
+
+/// Options for the fuzzing loop
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct FuzzOptions {
+    /// The number of randomly transformed copies of each instance to check
+    /// for metamorphic consistency
+    #[serde(default)]
+    pub metamorphic: u32,
+    /// The number of instances to test
+    pub instances: Option<usize>,
+    /// The wall-clock time budget in seconds
+    pub time_budget: Option<f64>,
+    /// Stop after this many unique bugs have been found
+    pub max_bugs: Option<usize>,
+    /// Keep fuzzing until interrupted
+    #[serde(default)]
+    pub until_interrupted: bool,
+    /// Sample random options for scuttle solvers on each instance. Options
+    /// given in the solver config are kept.
+    #[serde(default)]
+    pub random_options: bool,
+    /// The path to a `.toml` file listing known bugs to suppress
+    pub suppressions: Option<PathBuf>,
+    /// The directory to write found bugs to, with one subdirectory per bug.
+    /// Defaults to the working directory.
+    pub output: Option<PathBuf>,
+    /// The file format to write found instances in
+    #[serde(default)]
+    pub output_format: InstFormat,
+}
+
+impl FuzzOptions {
+    /// The number of instances to test, if limited
+    pub fn instances(&self) -> Option<usize> {
+        if self.until_interrupted {
+            return None;
+        }
+        match (self.instances, self.time_budget, self.max_bugs) {
+            (Some(n), _, _) => Some(n),
+            (None, None, None) => Some(DEFAULT_FUZZ_INSTANCES),
+            (None, _, _) => None,
+        }
+    }
+    pub fn time_budget(&self) -> Option<Duration> {
+        if self.until_interrupted {
+            return None;
+        }
+        self.time_budget.map(Duration::from_secs_f64)
+    }
+    pub fn max_bugs(&self) -> Option<usize> {
+        self.max_bugs
+    }
+}
+
+/// A suppression file listing known bugs
 #[derive(Deserialize)]
+pub struct SuppressionFile {
+    #[serde(default)]
+    pub suppress: Vec<Suppression>,
+}
+
+/// A known bug whose problems are counted but not reported
+#[derive(Deserialize, Clone, Debug)]
+pub struct Suppression {
+    /// The solver, as shown in the report
+    pub solver: String,
+    /// The kind of problem, as shown in the report. Matches all kinds if not
+    /// given.
+    pub kind: Option<String>,
+    /// A substring of the panic or error message
+    pub message: Option<String>,
+}
+
+impl Suppression {
+    /// Checks whether a problem of a solver matches the suppression
+    pub fn matches(&self, solver: &str, problem: &Problem) -> bool {
+        if self.solver != solver {
+            return false;
+        }
+        if self
+            .kind
+            .as_ref()
+            .is_some_and(|kind| kind != problem.kind())
+        {
+            return false;
+        }
+        match &self.message {
+            Some(sub) => match problem {
+                Problem::Panic(info) => info.message.contains(sub.as_str()),
+                Problem::SolverError(err) => err.contains(sub.as_str()),
+                _ => false,
+            },
+            None => true,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone)]
 pub struct MinimizeConfig {
+    /// The seed for shuffling during minimization
+    pub seed: Option<u64>,
     pub max_rounds: u8,
     pub min_clauses: Option<bool>,
     pub min_literals: Option<bool>,
@@ -237,12 +564,169 @@ impl TryFrom<Config> for MinimizeConfig {
     }
 }
 
-#[derive(Deserialize, Clone)]
-pub enum SolverConfig {
+#[derive(Deserialize, Serialize, Clone)]
+pub struct SolverConfig {
+    #[serde(flatten)]
+    pub solver: SolverKind,
+    /// The time limit for a single run in seconds. Solvers linked into fazzer
+    /// run in an isolated worker process if a limit is given.
+    time_limit: Option<f64>,
+    /// The memory limit for a single run in MB. Solvers linked into fazzer
+    /// run in an isolated worker process if a limit is given.
+    memory_limit: Option<u64>,
+    /// Run the solver in an isolated worker process. External solvers always
+    /// run in a separate process.
+    #[serde(default)]
+    pub isolate: bool,
+    /// The instances the solver can handle. Overrides the defaults of the
+    /// solver kind.
+    capabilities: Option<Capabilities>,
+    /// Options for scuttle solvers. Rejected for other solver kinds.
+    pub options: Option<ScuttleOptions>,
+    /// The SAT oracle of scuttle solvers
+    #[serde(default)]
+    pub oracle: OracleConfig,
+}
+
+impl SolverConfig {
+    pub fn new(solver: SolverKind) -> Self {
+        SolverConfig {
+            solver,
+            time_limit: None,
+            memory_limit: None,
+            isolate: false,
+            capabilities: None,
+            options: None,
+            oracle: OracleConfig::default(),
+        }
+    }
+    /// The serialized configuration to pass to an isolated worker. Limits
+    /// are enforced by the parent process.
+    pub fn worker_config(&self) -> String {
+        let config = SolverConfig {
+            solver: self.solver.clone(),
+            time_limit: None,
+            memory_limit: None,
+            isolate: false,
+            capabilities: None,
+            options: self.options.clone(),
+            oracle: self.oracle,
+        };
+        toml::to_string(&config).expect("failed to serialize solver config")
+    }
+    pub fn time_limit(&self) -> Option<Duration> {
+        self.time_limit.map(Duration::from_secs_f64)
+    }
+    pub fn memory_limit(&self) -> Option<u64> {
+        self.memory_limit
+    }
+    /// The name of the solver in reports, including a non-default oracle
+    pub fn label(&self, name: &str) -> String {
+        match self.oracle {
+            OracleConfig::Default => String::from(name),
+            oracle => format!("{} ({})", name, oracle),
+        }
+    }
+    pub fn capabilities(&self) -> Capabilities {
+        self.capabilities.unwrap_or_else(|| {
+            let mut caps = self.solver.default_capabilities();
+            if let SolverKind::Scuttle(_) = self.solver {
+                caps.enumeration = self
+                    .options
+                    .as_ref()
+                    .and_then(|opts| opts.enumeration)
+                    .is_some_and(|e| !matches!(e, EnumConfig::NoEnum));
+            }
+            caps
+        })
+    }
+}
+
+/// The SAT oracle that a scuttle solver runs on. Oracles other than the
+/// default need to be enabled with the Cargo feature of the same name. Kissat
+/// is not supported since it is not incremental.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OracleConfig {
+    /// The default incremental SAT solver of RustSAT
+    #[default]
+    Default,
+    Cadical,
+    Minisat,
+    Glucose,
+}
+
+impl OracleConfig {
+    /// Whether the oracle is enabled in this build
+    pub fn available(&self) -> bool {
+        match self {
+            OracleConfig::Default => true,
+            OracleConfig::Cadical => cfg!(feature = "cadical"),
+            OracleConfig::Minisat => cfg!(feature = "minisat"),
+            OracleConfig::Glucose => cfg!(feature = "glucose"),
+        }
+    }
+}
+
+impl fmt::Display for OracleConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OracleConfig::Default => write!(f, "default"),
+            OracleConfig::Cadical => write!(f, "cadical"),
+            OracleConfig::Minisat => write!(f, "minisat"),
+            OracleConfig::Glucose => write!(f, "glucose"),
+        }
+    }
+}
+
+/// The instances that a solver can handle. Solvers are skipped on instances
+/// they do not support.
+#[derive(Deserialize, Serialize, Clone, Copy, Default)]
+pub struct Capabilities {
+    /// The supported numbers of objectives. Any number if not given.
+    pub objectives: Option<U8Range>,
+    /// The maximum supported soft clause weight
+    pub max_weight: Option<usize>,
+    /// Whether the solver enumerates all solutions for each non-dominated
+    /// point
+    #[serde(default)]
+    pub enumeration: bool,
+}
+
+impl Capabilities {
+    pub fn supports(&self, n_objs: usize, max_weight: usize) -> bool {
+        self.objectives.is_none_or(|range| range.contains(n_objs))
+            && self.max_weight.is_none_or(|max| max_weight <= max)
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub enum SolverKind {
     Scuttle(ScuttleConfig),
+    External(ExternalConfig),
+    BruteForce(BruteForceConfig),
 }
 
-#[derive(Deserialize, Clone)]
+impl SolverKind {
+    fn default_capabilities(&self) -> Capabilities {
+        match self {
+            SolverKind::Scuttle(
+                ScuttleConfig::BiOptSatGte
+                | ScuttleConfig::BiOptSatDpw
+                | ScuttleConfig::DivConBiOptSat,
+            ) => Capabilities {
+                objectives: Some(U8Range { min: 2, max: 2 }),
+                ..Default::default()
+            },
+            SolverKind::BruteForce(conf) => Capabilities {
+                enumeration: conf.enumerate,
+                ..Default::default()
+            },
+            _ => Capabilities::default(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone)]
 pub enum ScuttleConfig {
     /// Default p-minimal algorithm
     PMinimal,
@@ -254,4 +738,279 @@ pub enum ScuttleConfig {
     BiOptSatDpw,
     /// Lower-bounding algorithm
     LowerBounding,
+    /// P-minimal algorithm with DPW
+    PMinimalDpw,
+    /// Lower-bounding algorithm with DPW
+    LowerBoundingDpw,
+    /// Divide and conquer with linear search anchor
+    DivConLinSu,
+    /// Divide and conquer with BiOptSat anchor
+    DivConBiOptSat,
+    /// Divide and conquer with p-minimal anchor, solving sub-problems of
+    /// `sub_problem_size`
+    DivConPMinimal,
+    /// Divide and conquer with lower-bounding anchor, solving sub-problems of
+    /// `sub_problem_size`
+    DivConLowerBounding,
+    /// Divide and conquer with n-minus anchor, recursing on `n_minus`
+    /// objectives less
+    DivConNMinus,
+}
+
+impl ScuttleConfig {
+    /// Whether the algorithm runs in the divide and conquer framework and
+    /// takes its options. The other algorithms only take the kernel options.
+    pub fn is_divcon(&self) -> bool {
+        !matches!(
+            self,
+            ScuttleConfig::PMinimal
+                | ScuttleConfig::PMinimalDpw
+                | ScuttleConfig::BiOptSatGte
+                | ScuttleConfig::BiOptSatDpw
+                | ScuttleConfig::LowerBounding
+                | ScuttleConfig::LowerBoundingDpw
+        )
+    }
+
+    /// Whether the algorithm takes the `sub_problem_size` option
+    pub fn takes_sub_problem_size(&self) -> bool {
+        matches!(
+            self,
+            ScuttleConfig::DivConPMinimal | ScuttleConfig::DivConLowerBounding
+        )
+    }
+
+    /// Whether the algorithm takes the `n_minus` option
+    pub fn takes_n_minus(&self) -> bool {
+        matches!(self, ScuttleConfig::DivConNMinus)
+    }
+}
+
+/// Options for scuttle solvers. Options that are not given keep the scuttle
+/// defaults.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct ScuttleOptions {
+    /// Solution enumeration
+    pub enumeration: Option<EnumConfig>,
+    /// Use solution-guided phasing
+    pub solution_guided_search: Option<bool>,
+    /// When to apply solution tightening
+    pub solution_tightening: Option<HeurImprConfig>,
+    /// Reserve all variables of the objective encodings upfront
+    pub reserve_enc_vars: Option<bool>,
+    /// Store the original clauses in the solver
+    pub store_cnf: Option<bool>,
+    /// Apply core trimming in core-guided phases
+    pub core_trimming: Option<bool>,
+    /// Apply core minimization in core-guided phases
+    pub core_minimization: Option<bool>,
+    /// Apply core exhaustion in core-guided phases
+    pub core_exhaustion: Option<bool>,
+    /// How to build the objective encodings after core boosting
+    pub build_encodings: Option<BuildEncodingsConfig>,
+    /// Rebase the objective encodings after core boosting
+    pub rebase_encodings: Option<bool>,
+    /// Reset the oracle after the global ideal point was found
+    pub reset_after_global_ideal: Option<bool>,
+    /// An inprocessing script to run after core boosting
+    pub inprocessing: Option<String>,
+    /// The sub-problem size for divide and conquer anchors that take one
+    pub sub_problem_size: Option<SubProblemSizeConfig>,
+    /// The parameter of the n-minus divide and conquer anchor
+    pub n_minus: Option<usize>,
+}
+
+impl ScuttleOptions {
+    /// Whether any option is set that only divide and conquer algorithms take
+    fn has_divcon_options(&self) -> bool {
+        self.build_encodings.is_some()
+            || self.rebase_encodings.is_some()
+            || self.reset_after_global_ideal.is_some()
+            || self.inprocessing.is_some()
+            || self.sub_problem_size.is_some()
+            || self.n_minus.is_some()
+    }
+}
+
+/// The size of divide and conquer sub-problems
+#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+pub enum SubProblemSizeConfig {
+    /// An absolute number of objectives
+    Abs(usize),
+    /// A number of objectives less than the full problem
+    Smaller(usize),
+}
+
+/// Solution enumeration for scuttle solvers
+#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+pub enum EnumConfig {
+    /// Return a single solution per non-dominated point
+    NoEnum,
+    /// Enumerate solutions, up to a limit per non-dominated point if given
+    Solutions { limit: Option<usize> },
+    /// Enumerate Pareto-MCSs, up to a limit per non-dominated point if given
+    PMCSs { limit: Option<usize> },
+}
+
+/// When to apply a heuristic improvement
+#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+pub enum HeurImprConfig {
+    Never,
+    Always,
+    OuterLoop,
+}
+
+/// How objective encodings are built after core boosting
+#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+pub enum BuildEncodingsConfig {
+    Once,
+    Rebuild,
+    CleanRebuild,
+}
+
+/// The brute-force reference solver
+#[derive(Deserialize, Serialize, Clone, Copy, Default)]
+pub struct BruteForceConfig {
+    /// Return all solutions for each non-dominated point
+    #[serde(default)]
+    pub enumerate: bool,
+}
+
+/// An external solver binary that is run on a temporary instance file
+#[derive(Deserialize, Serialize, Clone)]
+pub struct ExternalConfig {
+    /// The command to execute. Every occurrence of `{instance}` in the
+    /// arguments is replaced with the path to the instance file.
+    pub command: Vec<String>,
+    /// The file format to pass the instance in
+    #[serde(default)]
+    pub format: InstFormat,
+}
+
+/// File formats that instances can be written in
+#[derive(Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum InstFormat {
+    /// A DIMACS MCNF file
+    #[default]
+    Mcnf,
+    /// A multi-objective OPB file
+    Opb,
+}
+
+impl fmt::Display for InstFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.extension())
+    }
+}
+
+impl InstFormat {
+    /// The file extension of the format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            InstFormat::Mcnf => "mcnf",
+            InstFormat::Opb => "opb",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustsat::types::RsHashMap;
+
+    use super::{check_solvers, Config, InstConfig, SolverConfig, Suppression, SuppressionFile};
+    use crate::{eval::PanicInfo, Problem};
+
+    fn inst_config(path: &str) -> InstConfig {
+        let config: Config = toml::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        config.instances.unwrap()
+    }
+
+    #[test]
+    fn validate_inst_config() {
+        assert!(inst_config("configs/tiny.toml").validate().is_ok());
+        let mut config = inst_config("configs/tiny.toml");
+        config.xors[0].arity = 0;
+        assert!(config.validate().is_err());
+        let mut config = inst_config("configs/tiny.toml");
+        config.cards[0].min = config.cards[0].max + 1;
+        assert!(config.validate().is_err());
+        let mut config = inst_config("configs/tiny.toml");
+        config.pbs.as_mut().unwrap().max_coeff = 0;
+        assert!(config.validate().is_err());
+    }
+
+    fn scuttle_solver(alg: &str, options: &str) -> RsHashMap<String, SolverConfig> {
+        toml::from_str(&format!(
+            "[a]\nScuttle = \"{}\"\n[a.options]\n{}",
+            alg, options
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn scuttle_options_per_algorithm() {
+        assert!(check_solvers(&scuttle_solver("PMinimal", "store_cnf = true")).is_ok());
+        assert!(check_solvers(&scuttle_solver("PMinimal", "rebase_encodings = true")).is_err());
+        assert!(check_solvers(&scuttle_solver("BiOptSatDpw", "inprocessing = \"v\"")).is_err());
+        assert!(check_solvers(&scuttle_solver(
+            "CoreBoostedPMinimal",
+            "rebase_encodings = true"
+        ))
+        .is_ok());
+        assert!(check_solvers(&scuttle_solver("DivConNMinus", "n_minus = 2")).is_ok());
+        assert!(check_solvers(&scuttle_solver("DivConPMinimal", "n_minus = 2")).is_err());
+    }
+
+    fn suppressions(toml: &str) -> Vec<Suppression> {
+        toml::from_str::<SuppressionFile>(toml).unwrap().suppress
+    }
+
+    fn panic(message: &str) -> Problem {
+        Problem::Panic(PanicInfo {
+            message: String::from(message),
+            location: None,
+            backtrace: None,
+        })
+    }
+
+    #[test]
+    fn suppression_by_solver() {
+        let sup = &suppressions("[[suppress]]\nsolver = \"a\"")[0];
+        assert!(sup.matches("a", &Problem::Short));
+        assert!(sup.matches("a", &panic("index out of bounds")));
+        assert!(!sup.matches("b", &Problem::Short));
+    }
+
+    #[test]
+    fn suppression_by_kind() {
+        let sup = &suppressions("[[suppress]]\nsolver = \"a\"\nkind = \"panic\"")[0];
+        assert!(sup.matches("a", &panic("index out of bounds")));
+        assert!(!sup.matches("a", &Problem::Short));
+        assert!(!sup.matches("b", &panic("index out of bounds")));
+    }
+
+    #[test]
+    fn suppression_by_message() {
+        let sup = &suppressions("[[suppress]]\nsolver = \"a\"\nmessage = \"out of bounds\"")[0];
+        assert!(sup.matches("a", &panic("index out of bounds")));
+        assert!(sup.matches(
+            "a",
+            &Problem::SolverError(String::from("value out of bounds"))
+        ));
+        assert!(!sup.matches("a", &panic("attempt to subtract with overflow")));
+        // Problems without a message never match a message
+        assert!(!sup.matches("a", &Problem::Short));
+    }
+
+    #[test]
+    fn suppression_by_kind_and_message() {
+        let sup = &suppressions(
+            "[[suppress]]\nsolver = \"a\"\nkind = \"solver error\"\nmessage = \"out of bounds\"",
+        )[0];
+        assert!(sup.matches(
+            "a",
+            &Problem::SolverError(String::from("value out of bounds"))
+        ));
+        assert!(!sup.matches("a", &panic("index out of bounds")));
+    }
 }
@@ -1,59 +1,256 @@
 //! # Evaluating An Instance With a Solver
 
+use std::{
+    backtrace::{Backtrace, BacktraceStatus},
+    cell::{Cell, RefCell},
+    fmt,
+    panic::UnwindSafe,
+};
+
 use futures::{
     channel::{mpsc, oneshot},
     executor::ThreadPool,
     StreamExt,
 };
-use rustsat::{encodings::pb::DynamicPolyWatchdog, instances::MultiOptInstance, types::RsHashMap};
-use scuttle::types::ParetoFront;
+use rustsat::{
+    encodings::pb::{DbGte, DynamicPolyWatchdog},
+    instances::MultiOptInstance,
+    solvers::DefIncSolver,
+    types::RsHashMap,
+};
+use scuttle::{options::DivConAnchor, types::ParetoFront};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    config::{ScuttleConfig, SolverConfig},
-    Problem, Solver,
+    brute::BruteForce,
+    config::{CheckConfig, OracleConfig, ScuttleConfig, ScuttleOptions, SolverConfig, SolverKind},
+    external,
+    scuttle::{BiOptSat, DivCon, LowerBounding, Oracle, PMin},
+    verify, Problem, Solver,
 };
 
-pub fn evaluate<S: Solver + From<MultiOptInstance>>(
-    inst: MultiOptInstance,
-) -> Result<ParetoFront, Problem> {
-    std::panic::catch_unwind(|| {
-        let mut solver = S::from(inst);
+/// Information on a panic that occurred while evaluating a solver. Isolated
+/// workers pass it on to the parent process serialized as TOML.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PanicInfo {
+    pub message: String,
+    pub location: Option<String>,
+    /// Only captured if enabled via `RUST_BACKTRACE`
+    pub backtrace: Option<String>,
+}
+
+impl fmt::Display for PanicInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.location {
+            Some(loc) => write!(f, "{} at {}", self.message, loc)?,
+            None => write!(f, "{}", self.message)?,
+        }
+        if let Some(bt) = &self.backtrace {
+            write!(f, "\n{}", bt)?;
+        }
+        Ok(())
+    }
+}
+
+thread_local! {
+    /// Whether panics on this thread are captured rather than reported by the
+    /// default hook
+    static CAPTURE: Cell<bool> = const { Cell::new(false) };
+    static CAPTURED: RefCell<Option<PanicInfo>> = const { RefCell::new(None) };
+}
+
+/// Installs a panic hook that captures the information of panics in
+/// [`catch_panic`]. Other panics are passed on to the previous hook.
+pub fn install_panic_hook() {
+    let prev = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if !CAPTURE.with(Cell::get) {
+            return prev(info);
+        }
+        let payload = info.payload();
+        let message = if let Some(msg) = payload.downcast_ref::<&str>() {
+            String::from(*msg)
+        } else if let Some(msg) = payload.downcast_ref::<String>() {
+            msg.clone()
+        } else {
+            String::from("unknown panic payload")
+        };
+        let backtrace = Backtrace::capture();
+        let backtrace =
+            (backtrace.status() == BacktraceStatus::Captured).then(|| backtrace.to_string());
+        CAPTURED.with(|c| {
+            *c.borrow_mut() = Some(PanicInfo {
+                message,
+                location: info.location().map(|loc| loc.to_string()),
+                backtrace,
+            })
+        });
+    }));
+}
+
+/// Runs a closure and catches a panic in it, capturing the panic information
+pub fn catch_panic<R, F>(f: F) -> Result<R, PanicInfo>
+where
+    F: FnOnce() -> R + UnwindSafe,
+{
+    CAPTURE.with(|c| c.set(true));
+    let res = std::panic::catch_unwind(f);
+    CAPTURE.with(|c| c.set(false));
+    res.map_err(|_| {
+        CAPTURED
+            .with(|c| c.borrow_mut().take())
+            .unwrap_or_else(|| PanicInfo {
+                message: String::from("panic not captured"),
+                location: None,
+                backtrace: None,
+            })
+    })
+}
+
+/// Evaluates a solver on an instance in the current process. The solver is
+/// constructed with `init`, errors during construction are reported like
+/// errors while solving.
+pub fn evaluate<S, Init>(inst: MultiOptInstance, init: Init) -> Result<ParetoFront, Problem>
+where
+    S: Solver,
+    Init: FnOnce(MultiOptInstance) -> Result<S, String> + UnwindSafe,
+{
+    catch_panic(|| {
+        let mut solver = init(inst)?;
         solver.run()
     })
-    .map_err(|_| Problem::Panic)
+    .map_err(Problem::Panic)?
+    .map_err(Problem::SolverError)
+}
+
+/// Evaluates the solver described by a configuration on an instance. Solvers
+/// linked into fazzer run in an isolated worker process if they have a limit,
+/// so that the process can be killed when the limit is exceeded.
+pub fn evaluate_config(
+    sconf: &SolverConfig,
+    inst: MultiOptInstance,
+) -> Result<ParetoFront, Problem> {
+    let isolate = sconf.isolate || sconf.time_limit().is_some() || sconf.memory_limit().is_some();
+    match &sconf.solver {
+        SolverKind::Scuttle(_) | SolverKind::BruteForce(_) if isolate => {
+            external::run_isolated(sconf, inst)
+        }
+        SolverKind::Scuttle(conf) => {
+            let opts = sconf.options.clone().unwrap_or_default();
+            match sconf.oracle {
+                OracleConfig::Default => evaluate_scuttle::<DefIncSolver>(conf, inst, opts),
+                #[cfg(feature = "cadical")]
+                OracleConfig::Cadical => {
+                    evaluate_scuttle::<rustsat_cadical::CaDiCaL>(conf, inst, opts)
+                }
+                #[cfg(feature = "minisat")]
+                OracleConfig::Minisat => {
+                    evaluate_scuttle::<rustsat_minisat::core::Minisat>(conf, inst, opts)
+                }
+                #[cfg(feature = "glucose")]
+                OracleConfig::Glucose => {
+                    evaluate_scuttle::<rustsat_glucose::core::Glucose>(conf, inst, opts)
+                }
+                #[allow(unreachable_patterns)]
+                oracle => unreachable!("SAT oracle {} not enabled in this build", oracle),
+            }
+        }
+        SolverKind::BruteForce(conf) => {
+            let conf = *conf;
+            evaluate(inst, move |inst| Ok(BruteForce::new(inst, conf)))
+        }
+        SolverKind::External(conf) => {
+            catch_panic(|| external::run(conf, inst, sconf.time_limit(), sconf.memory_limit()))
+                .unwrap_or_else(|info| Err(Problem::Panic(info)))
+        }
+    }
+}
+
+/// Evaluates a scuttle solver running on the SAT oracle `O`
+fn evaluate_scuttle<O: Oracle>(
+    conf: &ScuttleConfig,
+    inst: MultiOptInstance,
+    opts: ScuttleOptions,
+) -> Result<ParetoFront, Problem> {
+    match conf {
+        ScuttleConfig::PMinimal => evaluate(inst, move |inst| PMin::<DbGte, O>::new(inst, &opts)),
+        ScuttleConfig::PMinimalDpw => evaluate(inst, move |inst| {
+            PMin::<DynamicPolyWatchdog, O>::new(inst, &opts)
+        }),
+        ScuttleConfig::CoreBoostedPMinimal => evaluate(inst, move |inst| {
+            DivCon::<O>::core_boosted_pmin(inst, &opts)
+        }),
+        ScuttleConfig::BiOptSatGte => {
+            evaluate(inst, move |inst| BiOptSat::<DbGte, O>::new(inst, &opts))
+        }
+        ScuttleConfig::BiOptSatDpw => evaluate(inst, move |inst| {
+            BiOptSat::<DynamicPolyWatchdog, O>::new(inst, &opts)
+        }),
+        ScuttleConfig::LowerBounding => evaluate(inst, move |inst| {
+            LowerBounding::<DbGte, O>::new(inst, &opts)
+        }),
+        ScuttleConfig::LowerBoundingDpw => evaluate(inst, move |inst| {
+            LowerBounding::<DynamicPolyWatchdog, O>::new(inst, &opts)
+        }),
+        ScuttleConfig::DivConLinSu => evaluate(inst, move |inst| {
+            DivCon::<O>::new(inst, &opts, DivConAnchor::LinSu)
+        }),
+        ScuttleConfig::DivConBiOptSat => evaluate(inst, move |inst| {
+            DivCon::<O>::new(inst, &opts, DivConAnchor::BiOptSat)
+        }),
+        ScuttleConfig::DivConPMinimal => evaluate(inst, move |inst| DivCon::<O>::pmin(inst, &opts)),
+        ScuttleConfig::DivConLowerBounding => {
+            evaluate(inst, move |inst| DivCon::<O>::lower_bounding(inst, &opts))
+        }
+        ScuttleConfig::DivConNMinus => {
+            evaluate(inst, move |inst| DivCon::<O>::n_minus(inst, &opts))
+        }
+    }
+}
+
+/// Checks whether a solver supports an instance according to its capabilities
+pub fn supported(sconf: &SolverConfig, inst: &MultiOptInstance) -> bool {
+    let (_, objs, _) = inst.clone().as_hard_cls_soft_cls();
+    let max_weight = objs
+        .iter()
+        .flat_map(|(softs, _)| softs.iter().map(|(_, w)| *w))
+        .max()
+        .unwrap_or(0);
+    sconf.capabilities().supports(objs.len(), max_weight)
+}
+
+/// The result of comparing multiple solvers on an instance
+pub struct Comparison {
+    pub problems: Vec<(String, Problem)>,
+    /// Solvers that were skipped since they do not support the instance
+    pub skipped: Vec<String>,
+    /// The Pareto fronts returned by the solvers, including invalid ones
+    pub fronts: Vec<(String, ParetoFront)>,
 }
 
 pub async fn compare(
     inst: MultiOptInstance,
     solvers: &RsHashMap<String, SolverConfig>,
+    checks: CheckConfig,
     pool: Option<ThreadPool>,
-) -> Vec<(String, Problem)> {
+) -> Comparison {
     let (tx_prob, rx_prob) = mpsc::channel::<(String, Problem)>(solvers.len());
     let (mut tx_pf, rx_pf) = mpsc::channel::<(String, ParetoFront)>(solvers.len());
 
     async {
+        let mut skipped = vec![];
         for (sid, sconf) in solvers {
+            if !supported(sconf, &inst) {
+                skipped.push(sid.clone());
+                continue;
+            }
             let sid = sid.clone();
             let sconf = sconf.clone();
             let inst = inst.clone();
             let mut pf_tx = tx_pf.clone();
             let mut prob_tx = tx_prob.clone();
             let fut_tx_result = async move {
-                let res = match sconf {
-                    SolverConfig::Scuttle(conf) => match conf {
-                        ScuttleConfig::PMinimal => evaluate::<crate::scuttle::PMin>(inst),
-                        ScuttleConfig::CoreBoostedPMinimal => {
-                            evaluate::<crate::scuttle::PMinCoreBoosting>(inst)
-                        }
-                        ScuttleConfig::BiOptSatGte => evaluate::<crate::scuttle::BiOptSat>(inst),
-                        ScuttleConfig::BiOptSatDpw => {
-                            evaluate::<crate::scuttle::BiOptSat<DynamicPolyWatchdog>>(inst)
-                        }
-                        ScuttleConfig::LowerBounding => {
-                            evaluate::<crate::scuttle::LowerBounding>(inst)
-                        }
-                    },
-                };
+                let res = evaluate_config(&sconf, inst);
                 match res {
                     Ok(pf) => pf_tx
                         .try_send((sid, pf))
@@ -72,12 +269,16 @@ pub async fn compare(
         tx_pf.disconnect();
 
         let nobjs = inst.n_objectives();
+        let mut fronts = vec![];
         let future_pfs = rx_pf
+            .inspect(|(sid, pf)| fronts.push((sid.clone(), pf.clone())))
             .filter(|(sid, pf)| {
                 filter_pf(
                     sid.clone(),
                     pf.clone(),
                     inst.clone(),
+                    checks,
+                    solvers[sid].capabilities().enumeration,
                     pool.clone(),
                     tx_prob.clone(),
                 )
@@ -87,7 +288,11 @@ pub async fn compare(
         compare_pfs(pfs, nobjs, pool, tx_prob).await;
 
         let fut_problems = rx_prob.collect();
-        fut_problems.await
+        Comparison {
+            problems: fut_problems.await,
+            skipped,
+            fronts,
+        }
     }
     .await
 }
@@ -96,12 +301,36 @@ async fn filter_pf(
     sid: String,
     pf: ParetoFront,
     inst: MultiOptInstance,
+    checks: CheckConfig,
+    enumeration: bool,
     pool: Option<ThreadPool>,
     mut tx_prob: mpsc::Sender<(String, Problem)>,
 ) -> bool {
     let (tx_filt, rx_filt) = oneshot::channel::<bool>();
     let future_prob = async move {
-        match check_pf(&pf, &inst) {
+        let res = check_pf(&pf, &inst)
+            .and_then(|_| {
+                if enumeration {
+                    check_enumeration(&pf)
+                } else {
+                    Ok(())
+                }
+            })
+            .and_then(|_| {
+                if checks.empty_fronts && pf.is_empty() {
+                    verify::check_unsat(&inst)
+                } else {
+                    Ok(())
+                }
+            })
+            .and_then(|_| {
+                if checks.completeness {
+                    verify::check_complete(&pf, &inst)
+                } else {
+                    Ok(())
+                }
+            });
+        match res {
             Ok(_) => tx_filt.send(true).expect("failed to send filter"),
             Err(prob) => {
                 tx_prob
@@ -120,14 +349,14 @@ async fn filter_pf(
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
-enum Relation {
+pub enum Relation {
     Incomparable,
     FirstDominates,
     SecondDominates,
     Equal,
 }
 
-fn check_relation(c1: &[isize], c2: &[isize]) -> Relation {
+pub fn check_relation(c1: &[isize], c2: &[isize]) -> Relation {
     let mut dom = Relation::Equal;
     for (c1, c2) in c1.iter().zip(c2.iter()) {
         if c1 < c2 {
@@ -145,7 +374,7 @@ fn check_relation(c1: &[isize], c2: &[isize]) -> Relation {
     dom
 }
 
-fn check_pf(pf: &ParetoFront, inst: &MultiOptInstance) -> Result<(), Problem> {
+pub fn check_pf(pf: &ParetoFront, inst: &MultiOptInstance) -> Result<(), Problem> {
     if pf.is_empty() {
         return Ok(());
     }
@@ -185,7 +414,24 @@ fn check_pf(pf: &ParetoFront, inst: &MultiOptInstance) -> Result<(), Problem> {
     Ok(())
 }
 
-/// Assumes that the Pareto fronts have already been individually checked
+/// Checks that an enumerating solver did not return any solution twice
+fn check_enumeration(pf: &ParetoFront) -> Result<(), Problem> {
+    for (ndom_idx, ndom) in pf.iter().enumerate() {
+        let sols: Vec<_> = ndom.iter().collect();
+        for idx1 in 0..sols.len() {
+            for idx2 in idx1 + 1..sols.len() {
+                if sols[idx1] == sols[idx2] {
+                    return Err(Problem::RepeatedSol(ndom_idx, idx1, idx2));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Assumes that the Pareto fronts have already been individually checked. Runs
+/// that exceeded a limit do not have a Pareto front and are therefore not part
+/// of the comparison.
 async fn compare_pfs(
     mut pfs: Vec<(String, ParetoFront)>,
     nobjs: usize,
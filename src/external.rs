@@ -0,0 +1,409 @@
+//! # External Solver Processes
+//!
+//! External solvers (as well as isolated worker processes) are run on an
+//! instance file in a temporary location and are expected to print the Pareto
+//! front they found to `stdout` in the following line-based format:
+//!
+//! - `o <cost 1> ... <cost n>` starts a new non-dominated point with the
+//!   given objective values
+//! - `v <lit> ... <lit> [0]` adds a solution for the last non-dominated point,
+//!   with literals in DIMACS/IPASIR notation
+//!
+//! All other lines (e.g., comments starting with `c` or status lines starting
+//! with `s`) are ignored. A solver that prints no `o` lines has found the
+//! instance to be unsatisfiable.
+
+use std::{
+    fs,
+    io::{self, BufRead, Read, Write},
+    path::{Path, PathBuf},
+    process::{self, Child, Command, ExitStatus, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+use rustsat::{
+    instances::{fio::opb, MultiOptInstance},
+    types::{Assignment, Lit},
+};
+use scuttle::types::{NonDomPoint, ParetoFront};
+
+use crate::{
+    config::{ExternalConfig, InstFormat, SolverConfig},
+    eval::PanicInfo,
+    Problem,
+};
+
+/// The placeholder in the command template that is replaced by the instance
+/// path
+const INSTANCE_PLACEHOLDER: &str = "{instance}";
+
+/// Exit codes that are not considered an error. These are the ones used by
+/// MaxSAT solvers to signal the result.
+const OK_EXIT_CODES: [i32; 3] = [10, 20, 30];
+
+/// The exit codes of a shell whose command was not executable or not found
+const SHELL_EXEC_EXIT_CODES: [i32; 2] = [126, 127];
+
+/// The exit code of a Rust process that panicked
+pub const PANIC_EXIT_CODE: i32 = 101;
+
+/// The exit code of a worker process whose solver returned an error
+pub const SOLVER_ERROR_EXIT_CODE: i32 = 102;
+
+/// The interval in which to check whether a solver with a time limit has
+/// terminated
+const WAIT_INTERVAL: Duration = Duration::from_millis(10);
+
+static TMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// A temporary instance file that is removed when dropped
+struct TmpInstance(PathBuf);
+
+impl TmpInstance {
+    fn write(inst: MultiOptInstance, format: InstFormat) -> io::Result<Self> {
+        let path = std::env::temp_dir().join(format!(
+            "fazzer-{}-{}.{}",
+            process::id(),
+            TMP_COUNTER.fetch_add(1, Ordering::Relaxed),
+            format.extension()
+        ));
+        write_instance(inst, &path, format)?;
+        Ok(TmpInstance(path))
+    }
+
+    fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TmpInstance {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// Writes an instance to a file in the given format
+pub fn write_instance(inst: MultiOptInstance, path: &Path, format: InstFormat) -> io::Result<()> {
+    match format {
+        InstFormat::Mcnf => inst.to_dimacs_path(path),
+        InstFormat::Opb => inst.to_opb_path(path, opb::Options::default()),
+    }
+}
+
+/// Writes an instance in OPB format with comment lines after the OPB header
+pub fn write_opb<W: Write>(
+    writer: &mut W,
+    comments: &[String],
+    inst: MultiOptInstance,
+) -> io::Result<()> {
+    let mut buffer = vec![];
+    inst.to_opb(&mut buffer, opb::Options::default())?;
+    let buffer = String::from_utf8_lossy(&buffer);
+    let mut lines = buffer.lines().peekable();
+    // The size line has to stay the first line of the file
+    if let Some(size) = lines.next_if(|line| line.starts_with("* #variable=")) {
+        writeln!(writer, "{}", size)?;
+    }
+    for comment in comments {
+        writeln!(writer, "* {}", comment)?;
+    }
+    for line in lines {
+        writeln!(writer, "{}", line)?;
+    }
+    Ok(())
+}
+
+/// Checks whether a program exists and is executable. Programs without a path
+/// separator are searched for in `PATH`, like when running them.
+pub fn is_executable(program: &str) -> bool {
+    if program.contains('/') {
+        return executable_file(Path::new(program));
+    }
+    std::env::var_os("PATH").is_some_and(|paths| {
+        std::env::split_paths(&paths).any(|dir| executable_file(&dir.join(program)))
+    })
+}
+
+#[cfg(unix)]
+fn executable_file(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn executable_file(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// Runs an external solver on an instance and parses the Pareto front it
+/// outputs. Panics if the solver cannot be run, invalid output is reported as
+/// a solver error.
+///
+/// The memory limit (in MB) is enforced via `ulimit -v` and exceeding it is
+/// detected from the error output of the solver.
+pub fn run(
+    config: &ExternalConfig,
+    inst: MultiOptInstance,
+    time_limit: Option<Duration>,
+    memory_limit: Option<u64>,
+) -> Result<ParetoFront, Problem> {
+    assert!(!config.command.is_empty(), "empty external solver command");
+    let tmp = TmpInstance::write(inst, config.format).expect("failed to write instance file");
+    let path = tmp.path().to_string_lossy();
+    let args = config
+        .command
+        .iter()
+        .map(|arg| arg.replace(INSTANCE_PLACEHOLDER, &path));
+    let output = run_process(args, time_limit, memory_limit)?;
+    if !output.status.success()
+        && !output
+            .status
+            .code()
+            .is_some_and(|code| OK_EXIT_CODES.contains(&code))
+    {
+        return Err(output.problem(memory_limit, false));
+    }
+    parse_pareto_front(io::BufReader::new(&output.stdout[..]))
+        .map_err(|err| Problem::SolverError(format!("invalid solver output: {}", err)))
+}
+
+/// Runs a solver that is linked into fazzer in an isolated worker process, by
+/// re-executing the current binary with the hidden `worker` command. This
+/// allows for reporting aborts and segfaults instead of crashing the fuzzer.
+pub fn run_isolated(sconf: &SolverConfig, inst: MultiOptInstance) -> Result<ParetoFront, Problem> {
+    let exe = std::env::current_exe().expect("failed to get fazzer executable");
+    let tmp = TmpInstance::write(inst, InstFormat::Mcnf).expect("failed to write instance file");
+    let args = [
+        exe.to_string_lossy().into_owned(),
+        String::from("worker"),
+        String::from("--solver"),
+        sconf.worker_config(),
+        tmp.path().to_string_lossy().into_owned(),
+    ];
+    let output = run_process(args.into_iter(), sconf.time_limit(), sconf.memory_limit())?;
+    if !output.status.success() {
+        return Err(output.problem(sconf.memory_limit(), true));
+    }
+    parse_pareto_front(io::BufReader::new(&output.stdout[..]))
+        .map_err(|err| Problem::SolverError(format!("invalid worker output: {}", err)))
+}
+
+/// Writes a Pareto front in the format described in the module documentation
+pub fn write_pareto_front<W: Write>(writer: &mut W, pf: &ParetoFront) -> io::Result<()> {
+    for ndom in pf.iter() {
+        write!(writer, "o")?;
+        for cost in ndom.costs() {
+            write!(writer, " {}", cost)?;
+        }
+        writeln!(writer)?;
+        for sol in ndom.iter() {
+            write!(writer, "v")?;
+            for lit in sol.iter() {
+                write!(writer, " {}", lit.to_ipasir())?;
+            }
+            writeln!(writer, " 0")?;
+        }
+    }
+    Ok(())
+}
+
+/// The output of a terminated solver process
+struct Output {
+    status: ExitStatus,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+}
+
+impl Output {
+    /// Classifies why the process failed. If the process is an isolated
+    /// worker, its exit codes for panics and solver errors are reported as
+    /// such.
+    fn problem(&self, memory_limit: Option<u64>, worker: bool) -> Problem {
+        if memory_limit.is_some() && out_of_memory(&String::from_utf8_lossy(&self.stderr)) {
+            return Problem::OutOfMemory;
+        }
+        #[cfg(unix)]
+        if let Some(signal) = std::os::unix::process::ExitStatusExt::signal(&self.status) {
+            return Problem::Crash(signal);
+        }
+        match self.status.code() {
+            Some(PANIC_EXIT_CODE) if worker => {
+                // The worker writes the serialized panic information, unless
+                // it panicked outside of the solver
+                let stderr = String::from_utf8_lossy(&self.stderr);
+                let info = toml::from_str(&stderr).unwrap_or_else(|_| PanicInfo {
+                    message: String::from(stderr.trim()),
+                    location: None,
+                    backtrace: None,
+                });
+                Problem::Panic(info)
+            }
+            Some(SOLVER_ERROR_EXIT_CODE) if worker => {
+                Problem::SolverError(String::from(String::from_utf8_lossy(&self.stderr).trim()))
+            }
+            // With a memory limit, the solver is started by a shell
+            Some(code) if memory_limit.is_some() && SHELL_EXEC_EXIT_CODES.contains(&code) => {
+                Problem::NotStarted(String::from(String::from_utf8_lossy(&self.stderr).trim()))
+            }
+            Some(code) => Problem::ExitCode(code),
+            None => Problem::Crash(0),
+        }
+    }
+}
+
+/// Runs a process with the given arguments and resource limits, the first
+/// argument being the program
+fn run_process<Args: Iterator<Item = String>>(
+    mut args: Args,
+    time_limit: Option<Duration>,
+    memory_limit: Option<u64>,
+) -> Result<Output, Problem> {
+    let mut cmd = if let Some(mem) = memory_limit {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c")
+            .arg(format!("ulimit -v {} && exec \"$@\"", mem * 1024))
+            .arg("fazzer-solver")
+            .args(args);
+        cmd
+    } else {
+        let mut cmd = Command::new(args.next().expect("empty command"));
+        cmd.args(args);
+        cmd
+    };
+    // Run in a separate process group, so that Ctrl-C only reaches fazzer
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut cmd, 0);
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| Problem::NotStarted(err.to_string()))?;
+    let stdout = read_pipe(child.stdout.take().unwrap());
+    let stderr = read_pipe(child.stderr.take().unwrap());
+    let status = match time_limit {
+        Some(limit) => match wait_timeout(&mut child, limit) {
+            Some(status) => status,
+            None => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(Problem::Timeout);
+            }
+        },
+        None => child.wait().expect("failed to wait for solver process"),
+    };
+    Ok(Output {
+        status,
+        stdout: stdout.join().expect("failed to read solver output"),
+        stderr: stderr.join().expect("failed to read solver output"),
+    })
+}
+
+/// Reads a pipe to the end in a separate thread, so that the child process
+/// does not block on a full pipe
+fn read_pipe<R: Read + Send + 'static>(mut pipe: R) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = vec![];
+        let _ = pipe.read_to_end(&mut buf);
+        buf
+    })
+}
+
+/// Waits for a child process for at most the given duration. Returns [`None`]
+/// if the child is still running afterwards.
+fn wait_timeout(child: &mut Child, limit: Duration) -> Option<ExitStatus> {
+    let start = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait().expect("failed to wait for solver process") {
+            return Some(status);
+        }
+        if start.elapsed() >= limit {
+            return None;
+        }
+        thread::sleep(WAIT_INTERVAL);
+    }
+}
+
+/// Heuristically checks if a failed process ran out of memory
+fn out_of_memory(stderr: &str) -> bool {
+    let stderr = stderr.to_lowercase();
+    stderr.contains("bad_alloc")
+        || stderr.contains("out of memory")
+        || stderr.contains("memory allocation")
+        || stderr.contains("cannot allocate memory")
+}
+
+/// Parses a Pareto front in the format described in the module documentation
+pub fn parse_pareto_front<R: BufRead>(reader: R) -> io::Result<ParetoFront> {
+    let mut pf = ParetoFront::default();
+    let mut ndom: Option<NonDomPoint> = None;
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        if let Some(costs) = line.strip_prefix('o') {
+            if !(costs.is_empty() || costs.starts_with(char::is_whitespace)) {
+                continue;
+            }
+            let costs = costs
+                .split_whitespace()
+                .map(|c| c.parse::<isize>().map_err(invalid_data))
+                .collect::<io::Result<Vec<_>>>()?;
+            if let Some(ndom) = ndom.replace(NonDomPoint::new(costs)) {
+                pf.add_nd(ndom);
+            }
+        } else if let Some(lits) = line.strip_prefix('v') {
+            if !(lits.is_empty() || lits.starts_with(char::is_whitespace)) {
+                continue;
+            }
+            let Some(ndom) = ndom.as_mut() else {
+                return Err(invalid_data("solution before first non-dominated point"));
+            };
+            let mut sol = Assignment::default();
+            for lit in lits.split_whitespace() {
+                let lit = lit.parse::<i32>().map_err(invalid_data)?;
+                if lit == 0 {
+                    break;
+                }
+                sol.assign_lit(Lit::from_ipasir(lit).map_err(invalid_data)?);
+            }
+            ndom.add_sol(sol);
+        }
+    }
+    if let Some(ndom) = ndom {
+        pf.add_nd(ndom);
+    }
+    Ok(pf)
+}
+
+fn invalid_data<E: ToString>(err: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_pareto_front;
+
+    #[test]
+    fn parse_front() {
+        let output = "c comment\no 3 1\nv 1 -2 0\nv 1 2\no 1 3\nv -1 -2\ns OPTIMUM FOUND\n";
+        let pf = parse_pareto_front(output.as_bytes()).unwrap();
+        assert_eq!(pf.len(), 2);
+        assert_eq!(pf[0].costs(), &vec![3, 1]);
+        assert_eq!(pf[0].n_sols(), 2);
+        assert_eq!(pf[1].costs(), &vec![1, 3]);
+        assert_eq!(pf[1].n_sols(), 1);
+    }
+
+    #[test]
+    fn parse_unsat() {
+        let pf = parse_pareto_front("s UNSATISFIABLE\n".as_bytes()).unwrap();
+        assert!(pf.is_empty());
+    }
+
+    #[test]
+    fn parse_sol_before_point() {
+        assert!(parse_pareto_front("v 1 2 0\n".as_bytes()).is_err());
+    }
+}
@@ -1,34 +1,266 @@
 //! # Fuzzing MO-MaxSAT Solvers
 
-use futures::executor::{self, ThreadPool};
+use std::{
+    fmt, fs,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+
+use futures::{
+    channel::mpsc,
+    executor,
+    future::{self, FutureExt},
+    StreamExt,
+};
+use itertools::Itertools;
 use rand::{Rng, SeedableRng};
 use rand_chacha::ChaCha8Rng;
 use rustsat::{instances::MultiOptInstance, types::RsHashMap};
+use scuttle::types::ParetoFront;
+use serde::Serialize;
+use signal_hook::consts::SIGINT;
 
 use crate::{
-    config::{InstConfig, SolverConfig},
-    eval,
-    gen::MoGenerator,
-    Problem,
+    brute,
+    config::{
+        BruteForceConfig, CheckConfig, ExecConfig, FuzzConfig, FuzzOptions, InstFormat,
+        MinimizeConfig, ScuttleOptions, SolverConfig, SolverKind,
+    },
+    eval, external,
+    gen::{Features, MoGenerator},
+    meta, min, scuttle, Problem,
 };
 
+/// The name of the automatically added brute-force reference solver. Solvers
+/// in the config may not use this name.
+pub const BRUTE_FORCE_ORACLE: &str = "brute-force";
+
+/// The signature of a bug, used to group occurrences of the same bug across
+/// instances
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Signature {
+    pub solver: String,
+    pub kind: &'static str,
+    /// The panic location or message, or the error message. For problems on
+    /// transformed copies, the kind and detail of the problem on the copy.
+    pub detail: Option<String>,
+}
+
+impl Signature {
+    pub fn new(solver: String, problem: &Problem) -> Self {
+        let detail = match problem {
+            Problem::Panic(info) => Some(info.location.clone().unwrap_or(info.message.clone())),
+            Problem::SolverError(err) | Problem::NotStarted(err) => Some(err.clone()),
+            Problem::Crash(code) | Problem::ExitCode(code) => Some(code.to_string()),
+            Problem::OnCopy(prob, ..) => {
+                let inner = Signature::new(String::new(), prob);
+                Some(match inner.detail {
+                    Some(detail) => format!("{} ({})", inner.kind, detail),
+                    None => String::from(inner.kind),
+                })
+            }
+            _ => None,
+        };
+        Signature {
+            solver,
+            kind: problem.kind(),
+            detail,
+        }
+    }
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.detail {
+            Some(detail) => write!(f, "{}: {} ({})", self.solver, self.kind, detail),
+            None => write!(f, "{}: {}", self.solver, self.kind),
+        }
+    }
+}
+
+/// A unique bug found during fuzzing
+#[derive(Debug, Clone, Copy)]
+pub struct Bug {
+    /// The number of instances the bug occurred on
+    pub occurrences: usize,
+    /// The seed of the smallest instance the bug occurred on
+    pub seed: u64,
+    /// The size of the smallest instance the bug occurred on
+    pub size: min::Size,
+}
+
 #[derive(Default, Debug)]
 pub struct FuzzResult {
+    /// The number of instances any problem was found on
+    n_buggy: usize,
+    /// The unique bugs by signature
+    bugs: RsHashMap<Signature, Bug>,
+    /// The problems on the kept instances, i.e., the smallest instances of
+    /// all bugs
     by_inst: RsHashMap<u64, Vec<(String, Problem)>>,
     by_solver: RsHashMap<String, Vec<(u64, Problem)>>,
+    /// The original and reduced sizes of minimized instances. The reduced
+    /// size is `None` if the minimization was cancelled.
+    minimized: RsHashMap<u64, (min::Size, Option<min::Size>)>,
+    /// The number of instances each solver was skipped on
+    skipped: RsHashMap<String, usize>,
+    /// The number of occurrences of suppressed known bugs
+    suppressed: RsHashMap<Signature, usize>,
+    /// The generator features of buggy instances in swarm mode
+    features: RsHashMap<u64, Features>,
+    /// The randomly sampled options of solvers with problems
+    sampled: RsHashMap<u64, Vec<(String, ScuttleOptions)>>,
 }
 
 impl FuzzResult {
-    fn instance_results(&mut self, inst_seed: u64, problems: Vec<(String, Problem)>) {
+    /// Records the problems found on an instance. Returns `None` if the
+    /// instance is not the smallest one for any bug and should be discarded,
+    /// otherwise the seeds of the kept instances that it supersedes.
+    fn instance_results(
+        &mut self,
+        inst_seed: u64,
+        size: min::Size,
+        problems: &[(String, Problem)],
+        mut sampled: RsHashMap<String, ScuttleOptions>,
+        solvers: &RsHashMap<String, SolverConfig>,
+    ) -> Option<Vec<u64>> {
+        self.n_buggy += 1;
+        let mut keep = false;
+        let mut superseded = vec![];
+        for (slv, prob) in problems {
+            let sig = Signature::new(label(slv, solvers), prob);
+            match self.bugs.get_mut(&sig) {
+                Some(bug) => {
+                    bug.occurrences += 1;
+                    if size < bug.size {
+                        superseded.push(bug.seed);
+                        bug.seed = inst_seed;
+                        bug.size = size;
+                        keep = true;
+                    }
+                }
+                None => {
+                    self.bugs.insert(
+                        sig,
+                        Bug {
+                            occurrences: 1,
+                            seed: inst_seed,
+                            size,
+                        },
+                    );
+                    keep = true;
+                }
+            }
+        }
+        if !keep {
+            return None;
+        }
+        let mut sampled_probs = vec![];
+        // Report solvers by label to include non-default oracles
+        let problems: Vec<_> = problems
+            .iter()
+            .map(|(slv, prob)| {
+                let label = label(slv, solvers);
+                if let Some(opts) = sampled.remove(slv) {
+                    sampled_probs.push((label.clone(), opts));
+                }
+                (label, prob.clone())
+            })
+            .collect();
         for (slv, prob) in &problems {
             match self.by_solver.get_mut(slv) {
-                Some(probs) => probs.push((inst_seed, *prob)),
+                Some(probs) => probs.push((inst_seed, prob.clone())),
                 None => {
-                    self.by_solver.insert(slv.clone(), vec![(inst_seed, *prob)]);
+                    self.by_solver
+                        .insert(slv.clone(), vec![(inst_seed, prob.clone())]);
                 }
             }
         }
         self.by_inst.insert(inst_seed, problems);
+        if !sampled_probs.is_empty() {
+            self.sampled.insert(inst_seed, sampled_probs);
+        }
+        // Drop superseded instances that are not the smallest for another bug
+        superseded.sort_unstable();
+        superseded.dedup();
+        superseded.retain(|&seed| !self.bugs.values().any(|bug| bug.seed == seed));
+        for seed in &superseded {
+            self.by_inst.remove(seed);
+            for probs in self.by_solver.values_mut() {
+                probs.retain(|(s, _)| s != seed);
+            }
+            self.minimized.remove(seed);
+            self.features.remove(seed);
+            self.sampled.remove(seed);
+        }
+        self.by_solver.retain(|_, probs| !probs.is_empty());
+        Some(superseded)
+    }
+
+    /// Checks whether an instance is the smallest one of a bug
+    fn is_smallest(&self, inst_seed: u64, sig: &Signature) -> bool {
+        self.bugs.get(sig).is_some_and(|bug| bug.seed == inst_seed)
+    }
+
+    /// Records and writes a minimized instance, if the original instance has
+    /// not been superseded in the meantime
+    fn instance_minimized(
+        &mut self,
+        inst_seed: u64,
+        orig: min::Size,
+        inst: MultiOptInstance,
+        out_dir: &Path,
+        format: InstFormat,
+    ) {
+        if !self.by_inst.contains_key(&inst_seed) {
+            return;
+        }
+        self.minimized
+            .insert(inst_seed, (orig, Some(min::Size::from(&inst))));
+        let path = bug_dir(out_dir, inst_seed).join(format!("instance.min.{}", format.extension()));
+        external::write_instance(inst, &path, format).expect("failed to write minimized instance");
+    }
+
+    /// Records an instance whose minimization was cancelled
+    fn instance_not_minimized(&mut self, inst_seed: u64, orig: min::Size) {
+        if !self.by_inst.contains_key(&inst_seed) {
+            return;
+        }
+        self.minimized.entry(inst_seed).or_insert((orig, None));
+    }
+
+    fn instance_suppressed(
+        &mut self,
+        suppressed: Vec<(String, Problem)>,
+        solvers: &RsHashMap<String, SolverConfig>,
+    ) {
+        for (slv, prob) in suppressed {
+            let sig = Signature::new(label(&slv, solvers), &prob);
+            *self.suppressed.entry(sig).or_insert(0) += 1;
+        }
+    }
+
+    fn instance_skipped(&mut self, skipped: Vec<String>) {
+        for slv in skipped {
+            *self.skipped.entry(slv).or_insert(0) += 1;
+        }
+    }
+
+    pub fn n_buggy(&self) -> usize {
+        self.n_buggy
+    }
+
+    pub fn n_bugs(&self) -> usize {
+        self.bugs.len()
+    }
+
+    pub fn bugs(&self) -> impl Iterator<Item = (&Signature, &Bug)> {
+        self.bugs.iter()
     }
 
     pub fn n_problems(&self) -> usize {
@@ -58,34 +290,509 @@ impl FuzzResult {
     pub fn solver_problems(&self) -> impl Iterator<Item = (&String, &Vec<(u64, Problem)>)> {
         self.by_solver.iter()
     }
+
+    pub fn skipped(&self) -> impl Iterator<Item = (&String, &usize)> {
+        self.skipped.iter()
+    }
+
+    pub fn suppressed(&self) -> impl Iterator<Item = (&Signature, &usize)> {
+        self.suppressed.iter()
+    }
+
+    pub fn n_features(&self) -> usize {
+        self.features.len()
+    }
+
+    pub fn features(&self) -> impl Iterator<Item = (&u64, &Features)> {
+        self.features.iter()
+    }
+
+    pub fn sampled_options(&self) -> impl Iterator<Item = (&u64, &Vec<(String, ScuttleOptions)>)> {
+        self.sampled.iter()
+    }
+
+    pub fn n_minimized(&self) -> usize {
+        self.minimized.len()
+    }
+
+    pub fn minimized(&self) -> impl Iterator<Item = (&u64, &(min::Size, Option<min::Size>))> {
+        self.minimized.iter()
+    }
 }
 
-pub fn fuzz(
-    mut config: InstConfig,
-    solvers: &RsHashMap<String, SolverConfig>,
-    pool: Option<ThreadPool>,
-) -> (usize, FuzzResult) {
+pub fn fuzz(fuzz_config: FuzzConfig) -> (usize, FuzzResult) {
+    let FuzzConfig {
+        pool,
+        execution,
+        instances: mut config,
+        solvers,
+        minimization,
+        checks,
+        options,
+        suppressions,
+    } = fuzz_config;
     let mut rng = match config.seed {
         Some(seed) => ChaCha8Rng::seed_from_u64(seed),
         None => ChaCha8Rng::from_entropy(),
     };
+    // The first Ctrl-C finishes the current instance, the second one exits
+    let interrupted = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register_conditional_shutdown(SIGINT, 1, Arc::clone(&interrupted))
+        .expect("failed to register signal handler");
+    signal_hook::flag::register(SIGINT, Arc::clone(&interrupted))
+        .expect("failed to register signal handler");
+    let out_dir = options.output.clone().unwrap_or_else(|| PathBuf::from("."));
+    fs::create_dir_all(&out_dir).expect("failed to create output directory");
+    let start = Instant::now();
     let mut results = FuzzResult::default();
     let mut tested = 0;
-    for _ in 0..5 {
+    let (tx_min, mut rx_min) = mpsc::unbounded::<(u64, min::Size, MultiOptInstance)>();
+    let mut pending_min = vec![];
+    loop {
+        if interrupted.load(Ordering::Relaxed)
+            || options.instances().is_some_and(|n| tested >= n)
+            || options.time_budget().is_some_and(|b| start.elapsed() >= b)
+            || options.max_bugs().is_some_and(|n| results.n_bugs() >= n)
+        {
+            break;
+        }
         loop {
             config.seed = Some(rng.gen());
             if !results.by_inst.contains_key(&config.seed.unwrap()) {
                 break;
             }
         }
-        let inst: MultiOptInstance = MultiOptInstance::from_iter(MoGenerator::new(config.clone()));
-        let probs = executor::block_on(eval::compare(inst.clone(), solvers, pool.clone()));
-        if !probs.is_empty() {
-            results.instance_results(config.seed.unwrap(), probs);
-            inst.to_dimacs_path(format!("buggy-{}.mcnf", config.seed.unwrap()))
-                .expect("failed to write instance");
+        let gen = MoGenerator::new(config.clone());
+        let features = gen.features().clone();
+        let inst: MultiOptInstance = MultiOptInstance::from_iter(gen);
+        let mut inst_solvers = solvers.clone();
+        let mut sampled = RsHashMap::default();
+        if options.random_options {
+            for (sid, sconf) in inst_solvers.iter_mut() {
+                if let SolverKind::Scuttle(alg) = &sconf.solver {
+                    let mut rng = ChaCha8Rng::seed_from_u64(config.seed.unwrap() ^ name_hash(sid));
+                    let opts = sconf.options.get_or_insert_with(ScuttleOptions::default);
+                    scuttle::randomize_options(alg, opts, &mut rng);
+                    sampled.insert(sid.clone(), opts.clone());
+                }
+            }
+        }
+        if checks
+            .brute_force_max_vars
+            .is_some_and(|max_vars| brute::n_vars(&inst) <= max_vars)
+        {
+            inst_solvers.insert(
+                String::from(BRUTE_FORCE_ORACLE),
+                SolverConfig::new(SolverKind::BruteForce(BruteForceConfig::default())),
+            );
+        }
+        let comparison = executor::block_on(eval::compare(
+            inst.clone(),
+            &inst_solvers,
+            checks,
+            pool.clone(),
+        ));
+        let mut probs = comparison.problems;
+        if options.metamorphic > 0 {
+            // The brute-force oracle is only a reference for the original instance
+            let meta_solvers: RsHashMap<_, _> = inst_solvers
+                .iter()
+                .filter(|(sid, _)| *sid != BRUTE_FORCE_ORACLE)
+                .map(|(sid, sconf)| (sid.clone(), sconf.clone()))
+                .collect();
+            probs.extend(executor::block_on(meta::check(
+                inst.clone(),
+                &meta_solvers,
+                &comparison.fronts,
+                options.metamorphic,
+                config.seed.unwrap(),
+                pool.clone(),
+            )));
+        }
+        results.instance_skipped(comparison.skipped);
+        let (suppressed, probs): (Vec<_>, Vec<_>) = probs.into_iter().partition(|(slv, prob)| {
+            let label = label(slv, &inst_solvers);
+            suppressions.iter().any(|sup| sup.matches(&label, prob))
+        });
+        results.instance_suppressed(suppressed, &inst_solvers);
+        let seed = config.seed.unwrap();
+        let kept = if probs.is_empty() {
+            None
+        } else {
+            let size = min::Size::from(&inst);
+            results.instance_results(seed, size, &probs, sampled.clone(), &inst_solvers)
+        };
+        // Only the smallest instance of each bug is kept
+        if let Some(superseded) = kept {
+            for old in superseded {
+                remove_bug(&out_dir, old);
+            }
+            let repro = ReproConfig {
+                execution,
+                solvers: &inst_solvers,
+                checks,
+                minimization: minimization.as_ref(),
+                fuzzing: &options,
+                sampled_options: &sampled,
+                metamorphic: probs
+                    .iter()
+                    .filter_map(|(slv, prob)| {
+                        let (trans, seed) = prob.transformations()?;
+                        Some(MetamorphicRepro {
+                            solver: slv,
+                            transformations: trans,
+                            seed,
+                            instance: format!("copy.{}.{}", slv, options.output_format.extension()),
+                        })
+                    })
+                    .collect(),
+            };
+            write_bug(
+                &bug_dir(&out_dir, seed),
+                seed,
+                options.output_format,
+                &inst,
+                &results.by_inst[&seed],
+                &comparison.fronts,
+                &repro,
+            )
+            .expect("failed to write bug");
+            if config.swarm {
+                results.features.insert(seed, features);
+            }
+            if let Some(min_config) = &minimization {
+                // Minimize a problem of a bug that this is the smallest instance of
+                let new_probs: Vec<_> = probs
+                    .into_iter()
+                    .filter(|(slv, prob)| {
+                        results.is_smallest(seed, &Signature::new(label(slv, &inst_solvers), prob))
+                    })
+                    .collect();
+                let target =
+                    min::Target::select(&new_probs, &min::TargetFilter::default()).unwrap();
+                // Minimization runs concurrently with fuzzing further instances
+                let min_config = min_config.clone();
+                let pool_min = pool.clone();
+                let tx_min = tx_min.clone();
+                let orig = min::Size::from(&inst);
+                let (future_min, handle) = future::abortable(async move {
+                    let inst = min::minimize(
+                        inst,
+                        target,
+                        &min_config,
+                        &inst_solvers,
+                        checks,
+                        pool_min,
+                        seed,
+                    )
+                    .await;
+                    tx_min
+                        .unbounded_send((seed, orig, inst))
+                        .expect("failed to send minimization result");
+                });
+                // Minimizations are aborted if fuzzing is stopped early
+                pending_min.push((seed, orig, handle));
+                let future_min = future_min.map(|_| ());
+                if let Some(ref pool) = pool {
+                    pool.spawn_ok(future_min);
+                } else {
+                    executor::block_on(future_min);
+                }
+            }
+        }
+        // Collect minimizations that finished in the meantime
+        while let Ok(Some((seed, orig, inst))) = rx_min.try_next() {
+            results.instance_minimized(seed, orig, inst, &out_dir, options.output_format);
         }
         tested += 1;
     }
+    drop(tx_min);
+    // Do not wait for pending minimizations when fuzzing was stopped
+    if interrupted.load(Ordering::Relaxed)
+        || options.time_budget().is_some_and(|b| start.elapsed() >= b)
+    {
+        pending_min.iter().for_each(|(_, _, handle)| handle.abort());
+    }
+    let minimized: Vec<_> = executor::block_on(rx_min.collect());
+    for (seed, orig, inst) in minimized {
+        results.instance_minimized(seed, orig, inst, &out_dir, options.output_format);
+    }
+    for (seed, orig, _) in pending_min {
+        results.instance_not_minimized(seed, orig);
+    }
     (tested, results)
 }
+
+/// The label of a solver in the results
+fn label(slv: &str, solvers: &RsHashMap<String, SolverConfig>) -> String {
+    match solvers.get(slv) {
+        Some(sconf) => sconf.label(slv),
+        None => String::from(slv),
+    }
+}
+
+/// The configuration to reproduce a bug with the `evaluate` or `minimize`
+/// commands. The solver configs include the sampled options, which are also
+/// listed separately along with the transformations of metamorphic problems.
+#[derive(Serialize)]
+struct ReproConfig<'a> {
+    execution: ExecConfig,
+    solvers: &'a RsHashMap<String, SolverConfig>,
+    checks: CheckConfig,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    minimization: Option<&'a MinimizeConfig>,
+    fuzzing: &'a FuzzOptions,
+    #[serde(skip_serializing_if = "RsHashMap::is_empty")]
+    sampled_options: &'a RsHashMap<String, ScuttleOptions>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    metamorphic: Vec<MetamorphicRepro<'a>>,
+}
+
+/// The transformations a metamorphic problem or a problem on a transformed
+/// copy of a solver was found with
+#[derive(Serialize)]
+struct MetamorphicRepro<'a> {
+    solver: &'a str,
+    transformations: &'a [meta::Transformation],
+    /// The seed the transformations were applied with
+    seed: u64,
+    /// The file the transformed copy is written to
+    instance: String,
+}
+
+/// The directory of the artifacts of a buggy instance
+fn bug_dir(out_dir: &Path, inst_seed: u64) -> PathBuf {
+    out_dir.join(format!("buggy-{}", inst_seed))
+}
+
+/// Writes the instance and the transformed copies problems were found on, the
+/// returned Pareto fronts, the problems, the config and commands to reproduce
+/// a bug into its directory
+fn write_bug(
+    dir: &Path,
+    inst_seed: u64,
+    format: InstFormat,
+    inst: &MultiOptInstance,
+    problems: &[(String, Problem)],
+    fronts: &[(String, ParetoFront)],
+    config: &ReproConfig,
+) -> io::Result<()> {
+    fs::create_dir_all(dir.join("fronts"))?;
+    let inst_file = format!("instance.{}", format.extension());
+    external::write_instance(inst.clone(), &dir.join(&inst_file), format)?;
+    for repro in &config.metamorphic {
+        let copy = meta::copy(inst.clone(), repro.transformations, repro.seed);
+        external::write_instance(copy, &dir.join(&repro.instance), format)?;
+    }
+    for (sid, pf) in fronts {
+        let mut writer = BufWriter::new(fs::File::create(
+            dir.join("fronts").join(format!("{}.pf", sid)),
+        )?);
+        external::write_pareto_front(&mut writer, pf)?;
+    }
+    let mut writer = BufWriter::new(fs::File::create(dir.join("problems.txt"))?);
+    for (slv, prob) in problems {
+        writeln!(writer, "{}: {}", slv, prob)?;
+    }
+    let toml = toml::to_string(config).map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+    fs::write(dir.join("config.toml"), toml)?;
+    let exe = shell_quote(&std::env::current_exe()?.to_string_lossy());
+    let mut writer = BufWriter::new(fs::File::create(dir.join("command.sh"))?);
+    writeln!(writer, "#!/bin/sh")?;
+    writeln!(writer, "# Found by: {}", std::env::args().format(" "))?;
+    writeln!(writer, "# Instance seed: {}", inst_seed)?;
+    writeln!(writer, "cd \"$(dirname \"$0\")\" || exit 1")?;
+    writeln!(writer, "{} evaluate config.toml {}", exe, inst_file)?;
+    for repro in &config.metamorphic {
+        writeln!(
+            writer,
+            "# Transformed copy for {}: {}",
+            repro.solver,
+            repro.transformations.iter().format(", ")
+        )?;
+        writeln!(
+            writer,
+            "{} evaluate config.toml {}",
+            exe,
+            shell_quote(&repro.instance)
+        )?;
+    }
+    Ok(())
+}
+
+/// Quotes a string for use as a single word in a POSIX shell
+fn shell_quote(word: &str) -> String {
+    format!("'{}'", word.replace('\'', "'\\''"))
+}
+
+/// Removes the directory of a buggy instance that was superseded by a smaller
+/// one
+fn remove_bug(out_dir: &Path, inst_seed: u64) {
+    let dir = bug_dir(out_dir, inst_seed);
+    if let Err(err) = fs::remove_dir_all(&dir) {
+        if err.kind() != io::ErrorKind::NotFound {
+            panic!("failed to remove {:?}: {}", dir, err);
+        }
+    }
+}
+
+/// A hash of a solver name that is stable across runs and platforms, for
+/// seeding per-solver random number generators
+fn name_hash(name: &str) -> u64 {
+    // 64 bit FNV-1a
+    name.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+    use rustsat::types::RsHashMap;
+
+    use super::{shell_quote, FuzzResult};
+    use crate::{eval::PanicInfo, min::Size, Problem};
+
+    fn size(n_vars: u32) -> Size {
+        Size {
+            n_vars,
+            n_clauses: 10,
+        }
+    }
+
+    fn panic(message: &str, location: &str) -> Problem {
+        Problem::Panic(PanicInfo {
+            message: String::from(message),
+            location: Some(String::from(location)),
+            backtrace: None,
+        })
+    }
+
+    fn record(
+        results: &mut FuzzResult,
+        seed: u64,
+        n_vars: u32,
+        problems: &[(&str, Problem)],
+    ) -> Option<Vec<u64>> {
+        let problems: Vec<_> = problems
+            .iter()
+            .map(|(slv, prob)| (String::from(*slv), prob.clone()))
+            .collect();
+        results.instance_results(
+            seed,
+            size(n_vars),
+            &problems,
+            RsHashMap::default(),
+            &RsHashMap::default(),
+        )
+    }
+
+    #[test]
+    fn signature_grouping() {
+        let mut results = FuzzResult::default();
+        record(
+            &mut results,
+            1,
+            5,
+            &[("a", panic("index out of bounds", "lib.rs:1:1"))],
+        );
+        // Panics at the same location are the same bug regardless of message
+        record(
+            &mut results,
+            2,
+            5,
+            &[("a", panic("other message", "lib.rs:1:1"))],
+        );
+        assert_eq!(results.n_bugs(), 1);
+        record(
+            &mut results,
+            3,
+            5,
+            &[("a", panic("index out of bounds", "lib.rs:2:1"))],
+        );
+        record(
+            &mut results,
+            4,
+            5,
+            &[("b", panic("index out of bounds", "lib.rs:1:1"))],
+        );
+        record(
+            &mut results,
+            5,
+            5,
+            &[("a", Problem::SolverError(String::from("error one")))],
+        );
+        record(
+            &mut results,
+            6,
+            5,
+            &[("a", Problem::SolverError(String::from("error two")))],
+        );
+        record(&mut results, 7, 5, &[("a", Problem::Short)]);
+        record(&mut results, 8, 5, &[("a", Problem::Short)]);
+        assert_eq!(results.n_buggy(), 8);
+        assert_eq!(results.n_bugs(), 6);
+        let occurrences: Vec<_> = results
+            .bugs()
+            .map(|(_, bug)| bug.occurrences)
+            .sorted()
+            .collect();
+        assert_eq!(occurrences, vec![1, 1, 1, 1, 2, 2]);
+    }
+
+    #[test]
+    fn smallest_instance_replacement() {
+        let mut results = FuzzResult::default();
+        assert_eq!(
+            record(&mut results, 1, 10, &[("a", Problem::Short)]),
+            Some(vec![])
+        );
+        // A larger instance of the same bug is discarded
+        assert_eq!(record(&mut results, 2, 20, &[("a", Problem::Short)]), None);
+        // A smaller instance supersedes the kept one
+        assert_eq!(
+            record(&mut results, 3, 5, &[("a", Problem::Short)]),
+            Some(vec![1])
+        );
+        let (_, bug) = results.bugs().next().unwrap();
+        assert_eq!(bug.seed, 3);
+        assert_eq!(bug.size, size(5));
+        assert_eq!(bug.occurrences, 3);
+        assert_eq!(results.n_instance_problems(1), 0);
+        assert_eq!(results.n_instance_problems(3), 1);
+        assert_eq!(results.n_solver_problems("a"), 1);
+    }
+
+    #[test]
+    fn smallest_instance_of_other_bug_kept() {
+        let mut results = FuzzResult::default();
+        record(
+            &mut results,
+            1,
+            10,
+            &[("a", Problem::Short), ("b", Problem::Short)],
+        );
+        // The first instance is still the smallest one for the bug of `b`
+        assert_eq!(
+            record(&mut results, 2, 5, &[("a", Problem::Short)]),
+            Some(vec![])
+        );
+        assert_eq!(results.n_instance_problems(1), 2);
+        assert_eq!(results.n_instance_problems(2), 1);
+        assert_eq!(record(&mut results, 3, 20, &[("b", Problem::Short)]), None);
+        assert_eq!(
+            record(&mut results, 4, 2, &[("b", Problem::Short)]),
+            Some(vec![1])
+        );
+        assert_eq!(results.n_instance_problems(1), 0);
+        assert_eq!(results.n_solver_problems("a"), 1);
+        assert_eq!(results.n_solver_problems("b"), 1);
+    }
+
+    #[test]
+    fn shell_quoting() {
+        assert_eq!(shell_quote("/usr/bin/fazzer"), "'/usr/bin/fazzer'");
+        assert_eq!(shell_quote("/my dir/fazzer"), "'/my dir/fazzer'");
+        assert_eq!(shell_quote("/it's/fazzer"), "'/it'\\''s/fazzer'");
+    }
+}
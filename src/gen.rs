@@ -1,34 +1,150 @@
 //! # Generate Random (Multi-Objective) MaxSAT Instances
 
-use std::ops::Range;
+use std::{
+    fmt,
+    ops::{Range, RangeInclusive},
+};
 
 use clap::crate_name;
+use itertools::Itertools;
 use rand::{Rng, SeedableRng};
 use rand_chacha::ChaCha8Rng;
 use rustsat::{
     clause,
-    instances::fio::dimacs,
-    types::{Clause, Lit, Var},
+    encodings::{
+        card::{self, Totalizer},
+        pb::{self, DbGte, DynamicPolyWatchdog},
+    },
+    instances::{
+        fio::dimacs, BasicVarManager, Cnf, ManageVars, MultiOptInstance, Objective, SatInstance,
+    },
+    types::{constraints::PBConstraint, Clause, Lit, Var},
 };
 
-use crate::config::InstConfig;
+use crate::config::{CardKind, InstConfig};
 
 const MAX_CL_LEN: u32 = 20;
+/// XOR constraints up to this arity are encoded directly, larger ones are
+/// split into a Tseitin chain with fresh variables
+const MAX_DIRECT_XOR_ARITY: u32 = 4;
 
 type Cl = (Option<(u8, usize)>, Clause);
 
+/// The generator features that are enabled for an instance. In swarm mode,
+/// features are randomly switched on or off per instance.
+#[derive(Debug, Clone)]
+pub struct Features {
+    pub equalities: bool,
+    pub ands: bool,
+    pub pbs: bool,
+    /// Soft clauses in layers
+    pub layer_softs: bool,
+    /// Soft equalities, ands, xors, cardinality and PB constraints
+    pub constraint_softs: bool,
+    /// The indices of the enabled XOR kinds
+    pub xor_kinds: Vec<usize>,
+    /// The indices of the enabled cardinality constraint kinds
+    pub card_kinds: Vec<usize>,
+    /// The indices of the enabled max weight variants
+    pub weight_variants: Vec<usize>,
+}
+
+impl Features {
+    fn all(n_xor_kinds: usize, n_card_kinds: usize, n_weight_variants: usize) -> Self {
+        Features {
+            equalities: true,
+            ands: true,
+            pbs: true,
+            layer_softs: true,
+            constraint_softs: true,
+            xor_kinds: (0..n_xor_kinds).collect(),
+            card_kinds: (0..n_card_kinds).collect(),
+            weight_variants: (0..n_weight_variants).collect(),
+        }
+    }
+
+    fn random<R: Rng>(
+        rng: &mut R,
+        n_xor_kinds: usize,
+        n_card_kinds: usize,
+        n_weight_variants: usize,
+    ) -> Self {
+        let mut weight_variants: Vec<usize> = (0..n_weight_variants)
+            .filter(|_| rng.gen_bool(0.5))
+            .collect();
+        if weight_variants.is_empty() {
+            weight_variants.push(rng.gen_range(0..n_weight_variants));
+        }
+        Features {
+            equalities: rng.gen_bool(0.5),
+            ands: rng.gen_bool(0.5),
+            pbs: rng.gen_bool(0.5),
+            layer_softs: rng.gen_bool(0.5),
+            constraint_softs: rng.gen_bool(0.5),
+            xor_kinds: (0..n_xor_kinds).filter(|_| rng.gen_bool(0.5)).collect(),
+            card_kinds: (0..n_card_kinds).filter(|_| rng.gen_bool(0.5)).collect(),
+            weight_variants,
+        }
+    }
+}
+
+impl fmt::Display for Features {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let enabled: Vec<&str> = [
+            (self.equalities, "equalities"),
+            (self.ands, "ands"),
+            (self.pbs, "pbs"),
+            (self.layer_softs, "layer-softs"),
+            (self.constraint_softs, "constraint-softs"),
+        ]
+        .into_iter()
+        .filter_map(|(on, name)| on.then_some(name))
+        .collect();
+        write!(
+            f,
+            "[{}] xor kinds {:?} card kinds {:?} weight variants {:?}",
+            enabled.join(", "),
+            self.xor_kinds,
+            self.card_kinds,
+            self.weight_variants
+        )
+    }
+}
+
 /// Generator for random multi-objective MaxSAT instances
 pub struct MoGenerator {
     rng: ChaCha8Rng,
     seed: Option<u64>,
+    features: Features,
+    /// Whether the features are randomly sampled in swarm mode
+    swarm: bool,
     objs: u8,
     layers: Vec<Layer>,
     arity: Vec<u32>,
     soft: Vec<u8>,
     eqs: u32,
     ands: u32,
-    xors3: u32,
-    xors4: u32,
+    /// The arity and number of the XOR constraints of each kind
+    xor_kinds: Vec<(u32, u32)>,
+    /// The arity of each XOR constraint
+    xor_arity: Vec<u32>,
+    /// The kind and number of the cardinality constraints of each kind
+    card_kinds: Vec<(CardKind, u32)>,
+    /// The kind of each cardinality constraint
+    cards: Vec<CardKind>,
+    pbs: u32,
+    pb_coeffs: RangeInclusive<usize>,
+    /// The maximum number of literals in an and, cardinality or PB constraint
+    max_arity: u32,
+    /// The precomputed CNF encodings of the cardinality and PB constraints
+    encoded: Vec<Cl>,
+    /// Whether PB constraints are kept native instead of being encoded
+    native_pbs: bool,
+    /// The native PB constraints, which are not part of the generated lines
+    native: Vec<PBConstraint>,
+    /// The planted assignment of the layer variables that all hard
+    /// constraints are satisfied by
+    planted: Option<Vec<bool>>,
     n_soft_left: Vec<u32>,
     weight_range: Range<usize>,
     weight_sum: usize,
@@ -39,6 +155,13 @@ pub struct MoGenerator {
 
 impl MoGenerator {
     pub fn new(config: InstConfig) -> Self {
+        Self::with_native_pbs(config, false)
+    }
+
+    /// Creates a generator that optionally keeps PB constraints native, for
+    /// output formats that support them. Native constraints are only
+    /// included in [`MoGenerator::into_instance`].
+    pub fn with_native_pbs(config: InstConfig, native_pbs: bool) -> Self {
         let mut gen = Self {
             rng: if let Some(seed) = config.seed {
                 ChaCha8Rng::seed_from_u64(seed)
@@ -46,14 +169,29 @@ impl MoGenerator {
                 ChaCha8Rng::from_entropy()
             },
             seed: config.seed,
+            features: Features::all(
+                config.xor_kinds(),
+                config.card_kinds(),
+                config.max_weight_variants(),
+            ),
+            swarm: config.swarm,
             objs: 0,
             layers: vec![],
             arity: vec![],
             soft: vec![],
             eqs: 0,
             ands: 0,
-            xors3: 0,
-            xors4: 0,
+            xor_kinds: vec![],
+            xor_arity: vec![],
+            card_kinds: vec![],
+            cards: vec![],
+            pbs: 0,
+            pb_coeffs: 1..=1,
+            max_arity: 0,
+            encoded: vec![],
+            native_pbs,
+            native: vec![],
+            planted: None,
             n_soft_left: vec![],
             weight_range: 0..0,
             weight_sum: 0,
@@ -65,7 +203,20 @@ impl MoGenerator {
         gen
     }
 
+    /// The generator features enabled for the instance
+    pub fn features(&self) -> &Features {
+        &self.features
+    }
+
     fn init(&mut self, config: InstConfig) {
+        if config.swarm {
+            self.features = Features::random(
+                &mut self.rng,
+                config.xor_kinds(),
+                config.card_kinds(),
+                config.max_weight_variants(),
+            );
+        }
         // generate layers
         let max_width = self.rng.gen_range(config.max_layer_width());
         self.layers = vec![Layer::default(); self.rng.gen_range(config.layers())];
@@ -84,7 +235,7 @@ impl MoGenerator {
             };
             let n_clauses = (self.rng.gen_range(config.layer_clauses()) * width_plus_last)
                 / config.layer_clauses_div();
-            let soft = if n_clauses > 4 * width_plus_last {
+            let soft = if self.features.layer_softs && n_clauses > 4 * width_plus_last {
                 self.rng.gen_range(1..=self.objs)
             } else {
                 0
@@ -100,21 +251,48 @@ impl MoGenerator {
                 unused,
             };
         }
+        if config.planted {
+            let n_vars = self.layers[self.layers.len() - 1].range.end;
+            self.planted = Some((0..n_vars).map(|_| self.rng.gen_bool(0.5)).collect());
+        }
         // generate counts
-        if self.rng.gen_bool(config.eqs_nonzero_prob()) {
+        if self.features.equalities && self.rng.gen_bool(config.eqs_nonzero_prob()) {
             self.eqs = self.rng.gen_range(config.eqs_range());
         }
-        if self.rng.gen_bool(config.ands_nonzero_prob()) {
+        if self.features.ands && self.rng.gen_bool(config.ands_nonzero_prob()) {
             self.ands = self.rng.gen_range(config.ands_range());
         }
-        if self.rng.gen_bool(config.xors3_nonzero_prob()) {
-            self.xors3 = self.rng.gen_range(config.xors3_range());
+        for kind in 0..config.xor_kinds() {
+            let mut n_xors = 0;
+            if self.features.xor_kinds.contains(&kind)
+                && self.rng.gen_bool(config.xors_nonzero_prob(kind))
+            {
+                n_xors = self.rng.gen_range(config.xors_range(kind));
+            }
+            let arity = config.xor_arity(kind);
+            self.xor_kinds.push((arity, n_xors));
+            self.xor_arity
+                .extend(std::iter::repeat(arity).take(n_xors as usize));
+        }
+        for kind in 0..config.card_kinds() {
+            let mut n_cards = 0;
+            if self.features.card_kinds.contains(&kind)
+                && self.rng.gen_bool(config.cards_nonzero_prob(kind))
+            {
+                n_cards = self.rng.gen_range(config.cards_range(kind));
+            }
+            let kind = config.card_kind(kind);
+            self.card_kinds.push((kind, n_cards));
+            self.cards
+                .extend(std::iter::repeat(kind).take(n_cards as usize));
         }
-        if self.rng.gen_bool(config.xors4_nonzero_prob()) {
-            self.xors4 = self.rng.gen_range(config.xors4_range());
+        if self.features.pbs && self.rng.gen_bool(config.pbs_nonzero_prob()) {
+            self.pbs = self.rng.gen_range(config.pbs_range());
         }
+        self.pb_coeffs = config.pb_coeffs();
         self.objs = self.rng.gen_range(config.objs());
-        let variant = self.rng.gen_range(0..config.max_weight_variants());
+        let variants = &self.features.weight_variants;
+        let variant = variants[self.rng.gen_range(0..variants.len())];
         let max_weight: usize = self
             .rng
             .gen_range(config.max_weight(variant))
@@ -130,12 +308,17 @@ impl MoGenerator {
         } else {
             self.layers[0].range.end - self.layers[0].range.start
         };
-        let max_arity = std::cmp::min(MAX_CL_LEN, width_plus_last / 2);
+        self.max_arity = std::cmp::min(MAX_CL_LEN, width_plus_last / 2);
         for arity in &mut self.arity {
-            *arity = self.rng.gen_range(2..=max_arity);
+            *arity = self.rng.gen_range(2..=self.max_arity);
         }
-        self.soft = vec![0; (self.ands + self.eqs + self.xors3 + self.xors4) as usize];
-        if self.objs > 0 {
+        self.soft = vec![
+            0;
+            (self.ands + self.eqs + self.pbs) as usize
+                + self.xor_arity.len()
+                + self.cards.len()
+        ];
+        if self.objs > 0 && self.features.constraint_softs {
             let all_soft = self.rng.gen_bool(1. / 10.);
             for s in &mut self.soft {
                 if all_soft || self.rng.gen_bool(1. / 5.) {
@@ -145,6 +328,40 @@ impl MoGenerator {
         }
         self.next_free_var = Var::new(self.layers[self.layers.len() - 1].range.end);
         self.n_soft_left = self.n_softs();
+        // encode cardinality and PB constraints upfront to know the number of clauses
+        for idx in 0..self.cards.len() as u32 {
+            let cls = self.card_clauses(idx);
+            self.encoded.extend(cls);
+        }
+        for idx in 0..self.pbs {
+            if self.native_pbs {
+                let (constr, soft) = self.native_pb(idx);
+                self.native.push(constr);
+                self.encoded.extend(soft);
+            } else {
+                let cls = self.pb_clauses(idx);
+                self.encoded.extend(cls);
+            }
+        }
+    }
+
+    /// Generates the whole instance. Since instances do not hold comments,
+    /// the header and layer comments are returned separately.
+    pub fn into_instance(mut self) -> (Vec<String>, MultiOptInstance) {
+        let mut comments = vec![];
+        let mut constr = SatInstance::default();
+        let mut objs = vec![Objective::default(); self.objs as usize];
+        for line in self.by_ref() {
+            match line {
+                dimacs::McnfLine::Comment(comment) => comments.push(comment),
+                dimacs::McnfLine::Hard(cl) => constr.add_clause(cl),
+                dimacs::McnfLine::Soft(cl, w, oidx) => objs[oidx].add_soft_clause(w, cl),
+            }
+        }
+        self.native
+            .drain(..)
+            .for_each(|pb| constr.add_pb_constr(pb));
+        (comments, MultiOptInstance::compose(constr, objs))
     }
 
     fn n_clauses(&self) -> u32 {
@@ -157,9 +374,11 @@ impl MoGenerator {
             cnt
         });
         n_cl += 2 * self.eqs;
-        n_cl += 4 * self.xors3;
-        n_cl += 8 * self.xors4;
-        n_cl
+        n_cl += self.encoded.iter().filter(|(w, _)| w.is_none()).count() as u32;
+        n_cl += self.native.len() as u32;
+        self.xor_arity
+            .iter()
+            .fold(n_cl, |cnt, &a| cnt + xor_n_clauses(a))
     }
 
     fn n_softs(&self) -> Vec<u32> {
@@ -193,8 +412,9 @@ impl MoGenerator {
         weight
     }
 
-    fn header_line(&self, id: u8) -> String {
-        match id {
+    /// The header comment with a given id, if it is emitted for the instance
+    fn header_line(&self, id: u8) -> Option<String> {
+        let line = match id {
             0 => format!("Generated by {}", crate_name!()),
             1 => {
                 if let Some(seed) = self.seed {
@@ -209,9 +429,36 @@ impl MoGenerator {
             5 => format!("{:?} soft clauses", self.n_softs()),
             6 => format!("equalitites {}", self.eqs),
             7 => format!("ands {}", self.ands),
-            8 => format!("xors3 {}", self.xors3),
-            _ => format!("xors4 {}", self.xors4),
-        }
+            8 => format!(
+                "xors {}",
+                self.xor_kinds
+                    .iter()
+                    .map(|(arity, n)| format!("{}x{}", n, arity))
+                    .format(", ")
+            ),
+            9 => format!(
+                "cards {}",
+                self.card_kinds
+                    .iter()
+                    .map(|(kind, n)| format!("{}x{}", n, kind))
+                    .format(", ")
+            ),
+            10 => format!("pbs {}", self.pbs),
+            11 => match &self.planted {
+                Some(planted) => format!(
+                    "planted {}",
+                    planted
+                        .iter()
+                        .enumerate()
+                        .map(|(idx, &val)| Lit::new(idx as u32, !val).to_ipasir())
+                        .format(" ")
+                ),
+                None => return None,
+            },
+            _ if self.swarm => format!("features {}", self.features),
+            _ => return None,
+        };
+        Some(line)
     }
 
     fn layer_desc(&self, idx: u8) -> String {
@@ -239,7 +486,7 @@ impl MoGenerator {
             None
         };
         let mut mark = vec![];
-        let mut cl = Clause::new();
+        let mut lits = vec![];
         let mut idx = 0;
         while idx < len {
             let mut l = lidx;
@@ -263,11 +510,14 @@ impl MoGenerator {
             if mark[lit.vidx()] {
                 continue;
             }
-            cl.add(lit);
+            lits.push(lit);
             mark[lit.vidx()] = true;
             idx += 1;
         }
-        (weight, cl)
+        if weight.is_none() {
+            self.plant_clause(&mut lits);
+        }
+        (weight, lits.into_iter().collect())
     }
 
     fn eq_clauses(&mut self, idx: u32) -> Vec<Cl> {
@@ -280,8 +530,14 @@ impl MoGenerator {
             return self.eq_clauses(idx);
         }
         let lit1 = Lit::new(v1, self.rng.gen_bool(0.5));
-        let lit2 = Lit::new(v2, self.rng.gen_bool(0.5));
+        let mut lit2 = Lit::new(v2, self.rng.gen_bool(0.5));
         let sidx = idx as usize;
+        if self.soft[sidx] == 0
+            && self.planted.is_some()
+            && self.planted_sat(lit1) == self.planted_sat(lit2)
+        {
+            lit2 = !lit2;
+        }
         if self.soft[sidx] > 0 {
             let blit = self.next_free_var.pos_lit();
             self.next_free_var += 1;
@@ -301,7 +557,7 @@ impl MoGenerator {
     fn and_clauses(&mut self, idx: u32) -> Vec<Cl> {
         debug_assert!(idx < self.ands);
         let layer = self.rng.gen_range(0..self.layers.len());
-        let lhs = Lit::new(
+        let mut lhs = Lit::new(
             self.rng.gen_range(self.layers[layer].range.clone()),
             self.rng.gen_bool(0.5),
         );
@@ -340,85 +596,387 @@ impl MoGenerator {
             ));
             cls
         } else {
+            if self.planted.is_some() {
+                let rhs_sat = cl.iter().skip(1).any(|&rhs| self.planted_sat(rhs));
+                if self.planted_sat(lhs) == rhs_sat {
+                    lhs = !lhs;
+                    cl = std::iter::once(lhs)
+                        .chain(cl.iter().skip(1).copied())
+                        .collect();
+                }
+            }
             let mut cls = vec![(None, cl.clone())];
             cls.extend(cl.drain(1..).map(|rhs| (None, clause![!lhs, !rhs])));
             cls
         }
     }
 
-    fn xor3_clauses(&mut self, idx: u32) -> Vec<Cl> {
-        debug_assert!(idx < self.xors3);
-        let mut lits = [Lit::new(0, false); 3];
-        for k in 0..3 {
-            let layer = self.rng.gen_range(0..self.layers.len());
-            lits[k] = Lit::new(
-                self.rng.gen_range(self.layers[layer].range.clone()),
-                self.rng.gen_bool(0.5),
-            );
-        }
+    fn xor_clauses(&mut self, idx: u32) -> Vec<Cl> {
+        debug_assert!((idx as usize) < self.xor_arity.len());
+        let mut lits: Vec<Lit> = (0..self.xor_arity[idx as usize])
+            .map(|_| {
+                let layer = self.rng.gen_range(0..self.layers.len());
+                Lit::new(
+                    self.rng.gen_range(self.layers[layer].range.clone()),
+                    self.rng.gen_bool(0.5),
+                )
+            })
+            .collect();
         let sidx = self.eqs as usize + self.ands as usize + idx as usize;
+        if self.soft[sidx] == 0
+            && self.planted.is_some()
+            && lits.iter().filter(|&&lit| self.planted_sat(lit)).count() % 2 == 0
+        {
+            lits[0] = !lits[0];
+        }
+        let mut cls: Vec<Cl> = xor_chain(&mut lits, &mut self.next_free_var)
+            .into_iter()
+            .map(|cl| (None, cl))
+            .collect();
         if self.soft[sidx] > 0 {
             let blit = self.next_free_var.pos_lit();
             self.next_free_var += 1;
-            vec![
-                (None, clause![blit, lits[0], lits[1], lits[2]]),
-                (None, clause![blit, lits[0], !lits[1], !lits[2]]),
-                (None, clause![blit, !lits[0], lits[1], !lits[2]]),
-                (None, clause![blit, !lits[0], !lits[1], lits[2]]),
-                (
-                    Some((self.soft[sidx] - 1, self.weight(self.soft[sidx] - 1))),
-                    clause![!blit],
-                ),
-            ]
+            cls.extend(relax(xor_direct(&lits), blit).map(|cl| (None, cl)));
+            cls.push((
+                Some((self.soft[sidx] - 1, self.weight(self.soft[sidx] - 1))),
+                clause![!blit],
+            ));
         } else {
-            vec![
-                (None, clause![lits[0], lits[1], lits[2]]),
-                (None, clause![lits[0], !lits[1], !lits[2]]),
-                (None, clause![!lits[0], lits[1], !lits[2]]),
-                (None, clause![!lits[0], !lits[1], lits[2]]),
-            ]
+            cls.extend(xor_direct(&lits).into_iter().map(|cl| (None, cl)));
+        }
+        cls
+    }
+
+    fn card_clauses(&mut self, idx: u32) -> Vec<Cl> {
+        debug_assert!((idx as usize) < self.cards.len());
+        let sidx = self.eqs as usize + self.ands as usize + self.xor_arity.len() + idx as usize;
+        let mut lits = self.distinct_lits();
+        let n = lits.len();
+        let kind = self.cards[idx as usize];
+        let k = if kind == CardKind::ExactlyOne {
+            1
+        } else {
+            self.rng.gen_range(1..n)
+        };
+        if self.soft[sidx] == 0 {
+            let (min, max) = match kind {
+                CardKind::AtMost => (0, k),
+                CardKind::AtLeast => (k, n),
+                CardKind::Exactly | CardKind::ExactlyOne => (k, k),
+            };
+            let mut weighted: Vec<(Lit, usize)> = lits.iter().map(|&lit| (lit, 1)).collect();
+            self.plant_sum(&mut weighted, min, max);
+            lits = weighted.into_iter().map(|(lit, _)| lit).collect();
+        }
+        let (defs, enforce) =
+            encode_card_kind(kind, &lits, k, |lits, ub| self.encode_card_ub(lits, ub));
+        self.constraint_clauses(sidx, defs, enforce)
+    }
+
+    /// Draws the literals and bound of a PB constraint. Returns the index of
+    /// the constraint in the soft assignments, the weighted literals, the
+    /// bound and whether it is an upper bound.
+    fn pb_constraint(&mut self, idx: u32) -> (usize, Vec<(Lit, usize)>, usize, bool) {
+        debug_assert!(idx < self.pbs);
+        let sidx = self.eqs as usize
+            + self.ands as usize
+            + self.xor_arity.len()
+            + self.cards.len()
+            + idx as usize;
+        let mut lits: Vec<(Lit, usize)> = self
+            .distinct_lits()
+            .into_iter()
+            .map(|lit| (lit, self.rng.gen_range(self.pb_coeffs.clone())))
+            .collect();
+        let sum: usize = lits.iter().map(|&(_, w)| w).sum();
+        let bound = self.rng.gen_range(1..sum);
+        let upper = self.rng.gen_bool(0.5);
+        if self.soft[sidx] == 0 {
+            if upper {
+                self.plant_sum(&mut lits, 0, bound);
+            } else {
+                self.plant_sum(&mut lits, bound, sum);
+            }
+        }
+        (sidx, lits, bound, upper)
+    }
+
+    fn pb_clauses(&mut self, idx: u32) -> Vec<Cl> {
+        let (sidx, lits, bound, upper) = self.pb_constraint(idx);
+        let (defs, enforce) =
+            encode_pb_bound(&lits, bound, upper, |lits, ub| self.encode_pb_ub(lits, ub));
+        self.constraint_clauses(sidx, defs, enforce)
+    }
+
+    /// Generates a native PB constraint. If the constraint is soft, it is
+    /// relaxed by a blocking literal and the soft clause is returned.
+    fn native_pb(&mut self, idx: u32) -> (PBConstraint, Option<Cl>) {
+        let (sidx, mut lits, mut bound, upper) = self.pb_constraint(idx);
+        // Draw the encoding choice of `encode_pb_ub` anyway, so that the rest
+        // of the instance is the same as with encoded PB constraints
+        let _ = self.rng.gen_bool(0.5);
+        let mut soft = None;
+        if self.soft[sidx] > 0 {
+            let blit = self.next_free_var.pos_lit();
+            self.next_free_var += 1;
+            // A true blocking literal satisfies the constraint on its own
+            if upper {
+                let sum: usize = lits.iter().map(|&(_, w)| w).sum();
+                lits.push((!blit, sum - bound));
+                bound = sum;
+            } else {
+                lits.push((blit, bound));
+            }
+            soft = Some((
+                Some((self.soft[sidx] - 1, self.weight(self.soft[sidx] - 1))),
+                clause![!blit],
+            ));
+        }
+        let lits = lits.into_iter().map(|(lit, w)| (lit, w as isize));
+        let constr = if upper {
+            PBConstraint::new_ub(lits, bound as isize)
+        } else {
+            PBConstraint::new_lb(lits, bound as isize)
+        };
+        (constr, soft)
+    }
+
+    /// Whether a literal is satisfied by the planted assignment
+    fn planted_sat(&self, lit: Lit) -> bool {
+        self.planted
+            .as_ref()
+            .is_some_and(|planted| planted[lit.vidx()] != lit.is_neg())
+    }
+
+    /// Negates a random literal of a hard clause, if the clause is not
+    /// satisfied by the planted assignment
+    fn plant_clause(&mut self, lits: &mut [Lit]) {
+        if self.planted.is_none() || lits.iter().any(|&lit| self.planted_sat(lit)) {
+            return;
+        }
+        let idx = self.rng.gen_range(0..lits.len());
+        lits[idx] = !lits[idx];
+    }
+
+    /// Negates random literals until the weighted sum of literals satisfied by
+    /// the planted assignment is within `min..=max`. Only terminates if no
+    /// single weight can jump over the entire range.
+    fn plant_sum(&mut self, lits: &mut [(Lit, usize)], min: usize, max: usize) {
+        if self.planted.is_none() {
+            return;
+        }
+        loop {
+            let sum: usize = lits
+                .iter()
+                .filter(|&&(lit, _)| self.planted_sat(lit))
+                .map(|&(_, w)| w)
+                .sum();
+            if (min..=max).contains(&sum) {
+                return;
+            }
+            // negate satisfied literals if the sum is too large and falsified
+            // ones if it is too small
+            let cands: Vec<usize> = (0..lits.len())
+                .filter(|&idx| self.planted_sat(lits[idx].0) == (sum > max))
+                .collect();
+            let idx = cands[self.rng.gen_range(0..cands.len())];
+            lits[idx].0 = !lits[idx].0;
         }
     }
 
-    fn xor4_clauses(&mut self, idx: u32) -> Vec<Cl> {
-        debug_assert!(idx < self.xors4);
-        let mut lits = [Lit::new(0, false); 4];
-        for k in 0..4 {
+    /// Draws literals of distinct variables for a cardinality or PB constraint
+    fn distinct_lits(&mut self) -> Vec<Lit> {
+        let n = self.rng.gen_range(2..=self.max_arity);
+        let mut mark = vec![];
+        let mut lits = vec![];
+        while lits.len() < n as usize {
             let layer = self.rng.gen_range(0..self.layers.len());
-            lits[k] = Lit::new(
+            let lit = Lit::new(
                 self.rng.gen_range(self.layers[layer].range.clone()),
                 self.rng.gen_bool(0.5),
             );
+            if lit.vidx() >= mark.len() {
+                mark.resize(lit.vidx() + 1, false);
+            }
+            if mark[lit.vidx()] {
+                continue;
+            }
+            mark[lit.vidx()] = true;
+            lits.push(lit);
+        }
+        lits
+    }
+
+    /// Encodes that at most `ub` of the literals are true with a randomly
+    /// selected encoding
+    fn encode_card_ub(&mut self, lits: &[Lit], ub: usize) -> (Cnf, Vec<Clause>) {
+        let weighted: Vec<(Lit, usize)> = lits.iter().map(|&lit| (lit, 1)).collect();
+        match self.rng.gen_range(0..3) {
+            0 => encode_card::<Totalizer>(lits, ub, &mut self.next_free_var),
+            1 => encode_pb::<DbGte>(&weighted, ub, &mut self.next_free_var),
+            _ => encode_pb::<DynamicPolyWatchdog>(&weighted, ub, &mut self.next_free_var),
+        }
+    }
+
+    /// Encodes that the weighted sum of the literals is at most `ub` with a
+    /// randomly selected encoding
+    fn encode_pb_ub(&mut self, lits: &[(Lit, usize)], ub: usize) -> (Cnf, Vec<Clause>) {
+        if self.rng.gen_bool(0.5) {
+            encode_pb::<DbGte>(lits, ub, &mut self.next_free_var)
+        } else {
+            encode_pb::<DynamicPolyWatchdog>(lits, ub, &mut self.next_free_var)
         }
-        let sidx = self.eqs as usize + self.ands as usize + self.xors3 as usize + idx as usize;
+    }
+
+    /// Collects the clauses of an encoded constraint. If the constraint is
+    /// soft, the clauses enforcing it are relaxed by a blocking literal.
+    fn constraint_clauses(&mut self, sidx: usize, defs: Cnf, enforce: Vec<Clause>) -> Vec<Cl> {
+        let mut cls: Vec<Cl> = defs.into_iter().map(|cl| (None, cl)).collect();
         if self.soft[sidx] > 0 {
             let blit = self.next_free_var.pos_lit();
             self.next_free_var += 1;
-            vec![
-                (None, clause![blit, lits[0], lits[1], lits[2], lits[3]]),
-                (None, clause![blit, lits[0], lits[1], !lits[2], !lits[3]]),
-                (None, clause![blit, lits[0], !lits[1], lits[2], !lits[3]]),
-                (None, clause![blit, lits[0], !lits[1], !lits[2], lits[3]]),
-                (None, clause![blit, !lits[0], lits[1], lits[2], !lits[3]]),
-                (None, clause![blit, !lits[0], lits[1], !lits[2], lits[3]]),
-                (None, clause![blit, !lits[0], !lits[1], lits[2], lits[3]]),
-                (None, clause![blit, !lits[0], !lits[1], !lits[2], !lits[3]]),
-                (
-                    Some((self.soft[sidx] - 1, self.weight(self.soft[sidx] - 1))),
-                    clause![!blit],
-                ),
-            ]
+            cls.extend(relax(enforce, blit).map(|cl| (None, cl)));
+            cls.push((
+                Some((self.soft[sidx] - 1, self.weight(self.soft[sidx] - 1))),
+                clause![!blit],
+            ));
         } else {
-            vec![
-                (None, clause![lits[0], lits[1], lits[2]]),
-                (None, clause![lits[0], !lits[1], !lits[2]]),
-                (None, clause![!lits[0], lits[1], !lits[2]]),
-                (None, clause![!lits[0], !lits[1], lits[2]]),
-            ]
+            cls.extend(enforce.into_iter().map(|cl| (None, cl)));
+        }
+        cls
+    }
+}
+
+/// Encodes a cardinality constraint of a given kind with bound `k` via an
+/// encoding of upper bounds. Returns the definitions of the encoding and the
+/// clauses enforcing the constraint.
+fn encode_card_kind<E>(
+    kind: CardKind,
+    lits: &[Lit],
+    k: usize,
+    mut encode_ub: E,
+) -> (Cnf, Vec<Clause>)
+where
+    E: FnMut(&[Lit], usize) -> (Cnf, Vec<Clause>),
+{
+    let n = lits.len();
+    let negated: Vec<Lit> = lits.iter().map(|&lit| !lit).collect();
+    match kind {
+        CardKind::AtMost => encode_ub(lits, k),
+        CardKind::AtLeast => encode_ub(&negated, n - k),
+        CardKind::Exactly => {
+            let (mut defs, mut enforce) = encode_ub(lits, k);
+            let (lb_defs, lb_enforce) = encode_ub(&negated, n - k);
+            defs.extend(lb_defs);
+            enforce.extend(lb_enforce);
+            (defs, enforce)
+        }
+        CardKind::ExactlyOne => {
+            let (defs, mut enforce) = encode_ub(lits, 1);
+            enforce.push(lits.iter().copied().collect());
+            (defs, enforce)
         }
     }
 }
 
+/// Encodes a PB constraint with an upper (`sum(w * lit) <= bound`) or lower
+/// (`sum(w * lit) >= bound`) bound via an encoding of upper bounds
+fn encode_pb_bound<E>(
+    lits: &[(Lit, usize)],
+    bound: usize,
+    upper: bool,
+    mut encode_ub: E,
+) -> (Cnf, Vec<Clause>)
+where
+    E: FnMut(&[(Lit, usize)], usize) -> (Cnf, Vec<Clause>),
+{
+    if upper {
+        return encode_ub(lits, bound);
+    }
+    // sum >= bound is equivalent to the negated sum <= sum - bound
+    let sum: usize = lits.iter().map(|&(_, w)| w).sum();
+    let negated: Vec<(Lit, usize)> = lits.iter().map(|&(lit, w)| (!lit, w)).collect();
+    encode_ub(&negated, sum - bound)
+}
+
+/// Relaxes the clauses enforcing a soft constraint by a blocking literal
+fn relax(cls: Vec<Clause>, blit: Lit) -> impl Iterator<Item = Clause> {
+    cls.into_iter().map(move |mut cl| {
+        cl.add(blit);
+        cl
+    })
+}
+
+/// Encodes a cardinality constraint `sum(lits) <= ub` with a cardinality
+/// encoding. Returns the definitions of the encoding and the clauses enforcing
+/// the bound.
+fn encode_card<CE>(lits: &[Lit], ub: usize, next_free_var: &mut Var) -> (Cnf, Vec<Clause>)
+where
+    CE: card::BoundUpper + FromIterator<Lit>,
+{
+    let mut var_manager = BasicVarManager::from_next_free(*next_free_var);
+    let mut enc: CE = lits.iter().copied().collect();
+    let defs = enc.encode_ub(ub..=ub, &mut var_manager);
+    let units = enc.enforce_ub(ub).expect("bound was just encoded");
+    *next_free_var = Var::new(var_manager.n_used());
+    (defs, units.into_iter().map(|lit| clause![lit]).collect())
+}
+
+/// Encodes a PB constraint `sum(w * lit) <= ub` with a PB encoding. Returns
+/// the definitions of the encoding and the clauses enforcing the bound.
+fn encode_pb<PBE>(lits: &[(Lit, usize)], ub: usize, next_free_var: &mut Var) -> (Cnf, Vec<Clause>)
+where
+    PBE: pb::BoundUpper + FromIterator<(Lit, usize)>,
+{
+    let mut var_manager = BasicVarManager::from_next_free(*next_free_var);
+    let mut enc: PBE = lits.iter().copied().collect();
+    let defs = enc.encode_ub(ub..=ub, &mut var_manager);
+    let units = enc.enforce_ub(ub).expect("bound was just encoded");
+    *next_free_var = Var::new(var_manager.n_used());
+    (defs, units.into_iter().map(|lit| clause![lit]).collect())
+}
+
+/// Directly encodes that the XOR of the literals is true, with one clause
+/// excluding each assignment of even parity
+fn xor_direct(lits: &[Lit]) -> Vec<Clause> {
+    (0..1u32 << lits.len())
+        .filter(|negs| negs.count_ones() % 2 == 0)
+        .map(|negs| {
+            lits.iter()
+                .enumerate()
+                .map(|(k, &lit)| if negs & (1 << k) != 0 { !lit } else { lit })
+                .collect()
+        })
+        .collect()
+}
+
+/// Shortens an XOR constraint to at most [`MAX_DIRECT_XOR_ARITY`] literals with
+/// a Tseitin chain: each chunk of literals is replaced by a fresh variable
+/// equal to their XOR. Returns the clauses defining the fresh variables.
+fn xor_chain(lits: &mut Vec<Lit>, next_free_var: &mut Var) -> Vec<Clause> {
+    let mut cls = vec![];
+    while lits.len() > MAX_DIRECT_XOR_ARITY as usize {
+        let tlit = next_free_var.pos_lit();
+        *next_free_var += 1;
+        let mut chunk: Vec<Lit> = lits.drain(..MAX_DIRECT_XOR_ARITY as usize - 1).collect();
+        chunk.push(!tlit);
+        cls.extend(xor_direct(&chunk));
+        lits.push(tlit);
+    }
+    cls
+}
+
+/// The number of hard clauses of an XOR constraint of the given arity
+fn xor_n_clauses(arity: u32) -> u32 {
+    let mut arity = arity;
+    let mut n_cl = 0;
+    while arity > MAX_DIRECT_XOR_ARITY {
+        n_cl += 1 << (MAX_DIRECT_XOR_ARITY - 1);
+        arity -= MAX_DIRECT_XOR_ARITY - 2;
+    }
+    n_cl + (1 << (arity - 1))
+}
+
 impl Iterator for MoGenerator {
     type Item = dimacs::McnfLine;
 
@@ -429,12 +987,14 @@ impl Iterator for MoGenerator {
         loop {
             match self.state {
                 LineType::Header(id) => {
-                    if id > 9 {
+                    if id > 12 {
                         self.state = LineType::LayerDesc(0);
                         continue;
                     }
                     self.state = LineType::Header(id + 1);
-                    return Some(dimacs::McnfLine::Comment(self.header_line(id)));
+                    if let Some(line) = self.header_line(id) {
+                        return Some(dimacs::McnfLine::Comment(line));
+                    }
                 }
                 LineType::LayerDesc(idx) => {
                     if idx as usize >= self.layers.len() {
@@ -468,7 +1028,7 @@ impl Iterator for MoGenerator {
                 }
                 LineType::AndCl(idx) => {
                     if idx >= self.ands {
-                        self.state = LineType::Xor3Cl(0);
+                        self.state = LineType::XorCl(0);
                         continue;
                     }
                     let mut cls = self.and_clauses(idx);
@@ -476,25 +1036,17 @@ impl Iterator for MoGenerator {
                     self.state = LineType::AndCl(idx + 1);
                     return Some(map_clause(cls.pop().unwrap()));
                 }
-                LineType::Xor3Cl(idx) => {
-                    if idx >= self.xors3 {
-                        self.state = LineType::Xor4Cl(0);
+                LineType::XorCl(idx) => {
+                    if idx as usize >= self.xor_arity.len() {
+                        self.state = LineType::EncodedCl;
                         continue;
                     }
-                    let mut cls = self.xor3_clauses(idx);
+                    let mut cls = self.xor_clauses(idx);
                     self.buffer.extend(cls.drain(1..));
-                    self.state = LineType::Xor3Cl(idx + 1);
-                    return Some(map_clause(cls.pop().unwrap()));
-                }
-                LineType::Xor4Cl(idx) => {
-                    if idx >= self.xors4 {
-                        return None;
-                    }
-                    let mut cls = self.xor4_clauses(idx);
-                    self.buffer.extend(cls.drain(1..));
-                    self.state = LineType::Xor4Cl(idx + 1);
+                    self.state = LineType::XorCl(idx + 1);
                     return Some(map_clause(cls.pop().unwrap()));
                 }
+                LineType::EncodedCl => return self.encoded.pop().map(map_clause),
             }
         }
     }
@@ -521,8 +1073,9 @@ enum LineType {
     LayerCl(u8, u32),
     EqCl(u32),
     AndCl(u32),
-    Xor3Cl(u32),
-    Xor4Cl(u32),
+    XorCl(u32),
+    /// Cardinality and PB constraints
+    EncodedCl,
 }
 
 impl Default for LineType {
@@ -537,17 +1090,217 @@ mod tests {
 
     use rustsat::instances::fio::dimacs;
 
-    use crate::config::{Config, InstConfig};
+    use crate::config::{CardKind, Config, InstConfig};
+
+    use rustsat::{
+        encodings::{
+            card::Totalizer,
+            pb::{DbGte, DynamicPolyWatchdog},
+        },
+        instances::Cnf,
+        solvers::{DefIncSolver, Solve, SolveIncremental, SolverResult},
+        types::{Clause, Lit, Var},
+    };
+
+    use super::{
+        encode_card, encode_card_kind, encode_pb, encode_pb_bound, relax, xor_chain, xor_direct,
+        xor_n_clauses, MoGenerator, MAX_DIRECT_XOR_ARITY,
+    };
+
+    type Encoded = (Cnf, Vec<Clause>);
 
-    use super::MoGenerator;
+    /// The encodings of upper bounds on cardinality constraints
+    const CARD_ENCODINGS: [fn(&[Lit], usize, &mut Var) -> Encoded; 3] = [
+        encode_card::<Totalizer>,
+        |lits, ub, next_free_var| encode_pb::<DbGte>(&unit_weights(lits), ub, next_free_var),
+        |lits, ub, next_free_var| {
+            encode_pb::<DynamicPolyWatchdog>(&unit_weights(lits), ub, next_free_var)
+        },
+    ];
+
+    /// The encodings of upper bounds on PB constraints
+    const PB_ENCODINGS: [fn(&[(Lit, usize)], usize, &mut Var) -> Encoded; 2] =
+        [encode_pb::<DbGte>, encode_pb::<DynamicPolyWatchdog>];
+
+    fn unit_weights(lits: &[Lit]) -> Vec<(Lit, usize)> {
+        lits.iter().map(|&lit| (lit, 1)).collect()
+    }
+
+    /// Checks for every assignment of the first `n` variables whether the
+    /// clauses can be satisfied, as expected by a predicate on the values.
+    /// Also checks that a blocking literal relaxes the enforcing clauses.
+    fn check_encoding<P>(n: u32, (defs, enforce): Encoded, next_free_var: Var, expected: P)
+    where
+        P: Fn(&[bool]) -> bool,
+    {
+        let blit = next_free_var.pos_lit();
+        let mut solver = DefIncSolver::default();
+        for cl in defs.into_iter().chain(relax(enforce, blit)) {
+            solver.add_clause(cl).unwrap();
+        }
+        for assign in 0..1u32 << n {
+            let vals: Vec<bool> = (0..n).map(|idx| (assign >> idx) & 1 == 1).collect();
+            let mut assumps: Vec<Lit> = (0..n)
+                .map(|idx| Lit::new(idx, !vals[idx as usize]))
+                .collect();
+            assumps.push(!blit);
+            let res = solver.solve_assumps(&assumps).unwrap();
+            assert_eq!(res == SolverResult::Sat, expected(&vals));
+            *assumps.last_mut().unwrap() = blit;
+            assert_eq!(solver.solve_assumps(&assumps).unwrap(), SolverResult::Sat);
+        }
+    }
 
     fn gen(config: &str, seed: u64) {
-        let mut config: Config = toml::from_str(&std::fs::read_to_string(config).unwrap()).unwrap();
-        let mut config = config.instances;
+        let config: Config = toml::from_str(&std::fs::read_to_string(config).unwrap()).unwrap();
+        let mut config = config.instances.unwrap();
         config.seed = Some(seed);
         dimacs::write_mcnf(&mut io::stdout(), MoGenerator::new(config)).unwrap();
     }
 
+    /// Checks that the planted assignment can be extended to a solution of
+    /// the hard clauses
+    fn planted(config: &str, seed: u64) {
+        let config: Config = toml::from_str(&std::fs::read_to_string(config).unwrap()).unwrap();
+        let mut config = config.instances.unwrap();
+        config.seed = Some(seed);
+        config.planted = true;
+        let gen = MoGenerator::new(config);
+        let assumps: Vec<Lit> = gen
+            .planted
+            .as_ref()
+            .unwrap()
+            .iter()
+            .enumerate()
+            .map(|(idx, &val)| Lit::new(idx as u32, !val))
+            .collect();
+        let mut solver = DefIncSolver::default();
+        for line in gen {
+            if let dimacs::McnfLine::Hard(cl) = line {
+                solver.add_clause(cl).unwrap();
+            }
+        }
+        assert_eq!(solver.solve_assumps(&assumps).unwrap(), SolverResult::Sat);
+    }
+
+    #[test]
+    fn planted42tiny() {
+        planted("configs/tiny.toml", 42)
+    }
+
+    #[test]
+    fn planted42small() {
+        planted("configs/small.toml", 42)
+    }
+
+    #[test]
+    fn xor_direct_parity() {
+        for arity in 1..=MAX_DIRECT_XOR_ARITY {
+            let lits: Vec<Lit> = (0..arity).map(|idx| Lit::new(idx, idx % 2 == 1)).collect();
+            let cls = xor_direct(&lits);
+            assert_eq!(cls.len() as u32, xor_n_clauses(arity));
+            for assign in 0..1u32 << arity {
+                let val = |lit: &Lit| ((assign >> lit.vidx()) & 1 == 1) != lit.is_neg();
+                let sat = cls.iter().all(|cl| cl.iter().any(|lit| val(lit)));
+                let odd = lits.iter().filter(|lit| val(lit)).count() % 2 == 1;
+                assert_eq!(sat, odd);
+            }
+        }
+    }
+
+    #[test]
+    fn xor_chain_parity() {
+        for arity in MAX_DIRECT_XOR_ARITY + 1..=2 * MAX_DIRECT_XOR_ARITY + 1 {
+            let orig: Vec<Lit> = (0..arity).map(|idx| Lit::new(idx, idx % 2 == 1)).collect();
+            let mut lits = orig.clone();
+            let mut next_free_var = Var::new(arity);
+            let mut cls = xor_chain(&mut lits, &mut next_free_var);
+            assert!(lits.len() as u32 <= MAX_DIRECT_XOR_ARITY);
+            cls.extend(xor_direct(&lits));
+            assert_eq!(cls.len() as u32, xor_n_clauses(arity));
+            let n_fresh = next_free_var.idx32() - arity;
+            for assign in 0..1u32 << arity {
+                // The fresh variables are determined by the original ones, so
+                // exactly one extension satisfies the chain for odd parity
+                let n_sat = (0..1u32 << n_fresh)
+                    .filter(|fresh| {
+                        let full = assign | (fresh << arity);
+                        let val = |lit: &Lit| ((full >> lit.vidx()) & 1 == 1) != lit.is_neg();
+                        cls.iter().all(|cl| cl.iter().any(|lit| val(lit)))
+                    })
+                    .count();
+                let val = |lit: &Lit| ((assign >> lit.vidx()) & 1 == 1) != lit.is_neg();
+                let odd = orig.iter().filter(|lit| val(lit)).count() % 2 == 1;
+                assert_eq!(n_sat, usize::from(odd));
+            }
+        }
+    }
+
+    #[test]
+    fn card_encodings() {
+        let n = 5;
+        let lits: Vec<Lit> = (0..n).map(|idx| Lit::new(idx, idx % 2 == 1)).collect();
+        let count = |vals: &[bool]| {
+            lits.iter()
+                .filter(|lit| vals[lit.vidx()] != lit.is_neg())
+                .count()
+        };
+        for encode in CARD_ENCODINGS {
+            for kind in [
+                CardKind::AtMost,
+                CardKind::AtLeast,
+                CardKind::Exactly,
+                CardKind::ExactlyOne,
+            ] {
+                for k in 1..n as usize {
+                    let mut next_free_var = Var::new(n);
+                    let encoded = encode_card_kind(kind, &lits, k, |lits, ub| {
+                        encode(lits, ub, &mut next_free_var)
+                    });
+                    check_encoding(n, encoded, next_free_var, |vals| match kind {
+                        CardKind::AtMost => count(vals) <= k,
+                        CardKind::AtLeast => count(vals) >= k,
+                        CardKind::Exactly => count(vals) == k,
+                        CardKind::ExactlyOne => count(vals) == 1,
+                    });
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pb_encodings() {
+        let n = 4;
+        let lits: Vec<(Lit, usize)> = [3, 1, 2, 2]
+            .into_iter()
+            .enumerate()
+            .map(|(idx, w)| (Lit::new(idx as u32, idx % 2 == 1), w))
+            .collect();
+        let sum = |vals: &[bool]| -> usize {
+            lits.iter()
+                .filter(|(lit, _)| vals[lit.vidx()] != lit.is_neg())
+                .map(|&(_, w)| w)
+                .sum()
+        };
+        for encode in PB_ENCODINGS {
+            for bound in 1..8 {
+                for upper in [true, false] {
+                    let mut next_free_var = Var::new(n);
+                    let encoded = encode_pb_bound(&lits, bound, upper, |lits, ub| {
+                        encode(lits, ub, &mut next_free_var)
+                    });
+                    check_encoding(n, encoded, next_free_var, |vals| {
+                        if upper {
+                            sum(vals) <= bound
+                        } else {
+                            sum(vals) >= bound
+                        }
+                    });
+                }
+            }
+        }
+    }
+
     #[test]
     fn gen42tiny() {
         gen("configs/tiny.toml", 42)
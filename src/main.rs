@@ -2,32 +2,41 @@
 
 use std::{any::Any, fmt, io, process::ExitCode};
 
-use itertools::Itertools;
 use ::scuttle::types::ParetoFront;
 use cli::{Cli, Exec};
+use config::InstFormat;
 use futures::executor;
 use gen::MoGenerator;
+use itertools::Itertools;
 use rustsat::instances::fio::dimacs;
 
 mod cli;
 mod config;
 mod eval;
+mod external;
 mod fuzz;
 mod gen;
+mod meta;
 mod min;
+mod verify;
 
 // Solver configs
+mod brute;
 mod scuttle;
 
 trait Solver {
-    /// Run the solver and get the discovered Pareto front
-    fn run(&mut self) -> ParetoFront;
+    /// Run the solver and get the discovered Pareto front, or an error
+    /// message if the solver failed
+    fn run(&mut self) -> Result<ParetoFront, String>;
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub enum Problem {
-    /// The solver panicked
-    Panic,
+    /// The solver panicked. The parameter holds the captured panic message,
+    /// location and backtrace.
+    Panic(eval::PanicInfo),
+    /// The solver returned an error. The parameter is the error message.
+    SolverError(String),
     /// Solution is not a solution to the constraints. The parameters are the
     /// index of the non-dominated point and the index of the solution.
     UnsatSol(usize, usize),
@@ -38,6 +47,10 @@ pub enum Problem {
     /// Repeated point in Pareto front. The parameter is the index of the
     /// repeated points in the Pareto front.
     Repeated(usize, usize),
+    /// An enumerating solver returned the same solution twice. The parameters
+    /// are the index of the non-dominated point and the indices of the
+    /// repeated solutions.
+    RepeatedSol(usize, usize, usize),
     /// The returned Pareto front is not non-dominated. The parameter is the
     /// index of the dominated point in the Pareto front.
     SelfDominated(usize),
@@ -50,12 +63,83 @@ pub enum Problem {
     /// The solver returned a non-dominated point with a wrong number of
     /// objective values.
     WrongDimension(usize),
+    /// The solver exceeded its time limit
+    Timeout,
+    /// The solver exceeded its memory limit
+    OutOfMemory,
+    /// The solver process was terminated by a signal. The parameter is the
+    /// signal number.
+    Crash(i32),
+    /// The solver process exited with an unexpected exit code
+    ExitCode(i32),
+    /// The solver process could not be started. The parameter is the error
+    /// message.
+    NotStarted(String),
+    /// The returned Pareto front is incomplete, as proven by a solution that
+    /// is not weakly dominated by any returned non-dominated point
+    Incomplete(verify::Witness),
+    /// The solver returned an empty Pareto front, but the instance is
+    /// satisfiable, as proven by the witness solution
+    WrongUnsat(verify::Witness),
+    /// The Pareto front of a transformed copy of the instance does not
+    /// correspond to the original Pareto front. The parameters are the
+    /// sequence of applied transformations and the seed they were applied
+    /// with.
+    Metamorphic(Vec<meta::Transformation>, u64),
+    /// The solver had a problem on a transformed copy of the instance. The
+    /// parameters are the problem on the copy and the transformations and
+    /// seed the copy was created with.
+    OnCopy(Box<Problem>, Vec<meta::Transformation>, u64),
+}
+
+impl Problem {
+    /// Whether the problem was detected by comparing to other solvers
+    pub fn is_comparison(&self) -> bool {
+        matches!(self, Problem::OtherDominated(_) | Problem::Short)
+    }
+
+    /// The transformations and seed of the copy of the instance the problem
+    /// was found on, if it was found on a transformed copy
+    pub fn transformations(&self) -> Option<(&[meta::Transformation], u64)> {
+        match self {
+            Problem::Metamorphic(trans, seed) | Problem::OnCopy(_, trans, seed) => {
+                Some((trans, *seed))
+            }
+            _ => None,
+        }
+    }
+
+    /// A short name of the kind of the problem, ignoring the parameters
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Problem::Panic(_) => "panic",
+            Problem::SolverError(_) => "solver error",
+            Problem::UnsatSol(..) => "unsat solution",
+            Problem::CostMismatch(..) => "cost mismatch",
+            Problem::Repeated(..) => "repeated point",
+            Problem::RepeatedSol(..) => "repeated enumerated solution",
+            Problem::SelfDominated(_) => "dominated by self",
+            Problem::OtherDominated(_) => "dominated by other",
+            Problem::Short => "pareto front too short",
+            Problem::WrongDimension(_) => "wrong dimension",
+            Problem::Timeout => "timeout",
+            Problem::OutOfMemory => "out of memory",
+            Problem::Crash(_) => "crash",
+            Problem::ExitCode(_) => "unexpected exit code",
+            Problem::NotStarted(_) => "not started",
+            Problem::Incomplete(_) => "pareto front incomplete",
+            Problem::WrongUnsat(_) => "wrongly unsatisfiable",
+            Problem::Metamorphic(..) => "metamorphic mismatch",
+            Problem::OnCopy(..) => "problem on transformed copy",
+        }
+    }
 }
 
 impl fmt::Display for Problem {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Problem::Panic => write!(f, "panicked"),
+            Problem::Panic(info) => write!(f, "panicked: {}", info),
+            Problem::SolverError(err) => write!(f, "solver error: {}", err),
             Problem::UnsatSol(ndi, si) => {
                 write!(f, "unsat solution (non-dom: {}, sol: {})", ndi, si)
             }
@@ -65,43 +149,192 @@ impl fmt::Display for Problem {
             Problem::Repeated(ndi, si) => {
                 write!(f, "repeated solution (non-dom: {}, sol: {}", ndi, si)
             }
+            Problem::RepeatedSol(ndi, si1, si2) => write!(
+                f,
+                "repeated enumerated solution (non-dom: {}, sols: {}, {})",
+                ndi, si1, si2
+            ),
             Problem::SelfDominated(ndi) => write!(f, "dominated by self (non-dom: {})", ndi),
             Problem::OtherDominated(ndi) => write!(f, "dominated by other (non-dom: {})", ndi),
             Problem::Short => write!(f, "pareto front too short"),
             Problem::WrongDimension(ndi) => {
                 write!(f, "point with wrong dimension (non-dom: {})", ndi)
             }
+            Problem::Timeout => write!(f, "exceeded time limit"),
+            Problem::OutOfMemory => write!(f, "exceeded memory limit"),
+            Problem::Crash(sig) => match signal_name(*sig) {
+                Some(name) => write!(f, "crashed ({})", name),
+                None => write!(f, "crashed (signal {})", sig),
+            },
+            Problem::ExitCode(code) => write!(f, "unexpected exit code {}", code),
+            Problem::NotStarted(err) => write!(f, "failed to start: {}", err),
+            Problem::Incomplete(wit) => {
+                write!(f, "pareto front incomplete (witness: {:?})", wit.costs)
+            }
+            Problem::WrongUnsat(wit) => {
+                write!(f, "wrongly unsatisfiable (witness: {:?})", wit.costs)
+            }
+            Problem::Metamorphic(trans, seed) => write!(
+                f,
+                "metamorphic mismatch ({}, seed {})",
+                trans.iter().format(", "),
+                seed
+            ),
+            Problem::OnCopy(prob, trans, seed) => write!(
+                f,
+                "{} on transformed copy ({}, seed {})",
+                prob,
+                trans.iter().format(", "),
+                seed
+            ),
         }
     }
 }
 
+fn signal_name(sig: i32) -> Option<&'static str> {
+    match sig {
+        4 => Some("SIGILL"),
+        6 => Some("SIGABRT"),
+        7 => Some("SIGBUS"),
+        8 => Some("SIGFPE"),
+        9 => Some("SIGKILL"),
+        11 => Some("SIGSEGV"),
+        _ => None,
+    }
+}
+
 fn main() -> ExitCode {
     let (cli, exec) = Cli::init();
+    eval::install_panic_hook();
 
     match exec {
-        Exec::Generate(config) => dimacs::write_mcnf(&mut io::stdout(), MoGenerator::new(config))
-            .unwrap_or_else(panic_with_err!(&cli)),
+        Exec::Generate(config, InstFormat::Mcnf) => {
+            dimacs::write_mcnf(&mut io::stdout(), MoGenerator::new(config))
+                .unwrap_or_else(panic_with_err!(&cli))
+        }
+        Exec::Generate(config, InstFormat::Opb) => {
+            let (comments, inst) = MoGenerator::with_native_pbs(config, true).into_instance();
+            external::write_opb(&mut io::stdout(), &comments, inst)
+                .unwrap_or_else(panic_with_err!(&cli))
+        }
         Exec::Fuzz(config) => {
             cli.info(&format!(
                 "fuzzing {:?}",
                 config.solvers.keys().collect::<Vec<_>>()
             ));
-            let (tested, results) = fuzz::fuzz(config.instances, &config.solvers, config.pool);
+            let (tested, results) = fuzz::fuzz(config);
             cli.info(&format!("tested {} instances", tested));
-            if results.n_problems() > 0 {
-                cli.warning(&format!("found {} problems", results.n_problems()));
+            for (sig, cnt) in results.suppressed() {
+                cli.info(&format!("suppressed {} occurrences of {}", cnt, sig));
+            }
+            if results.n_bugs() > 0 {
+                cli.warning(&format!(
+                    "found {} unique bugs on {} instances",
+                    results.n_bugs(),
+                    results.n_buggy()
+                ));
+                cli.print_bugs(results.bugs());
                 cli.print_instance_problems(results.instance_problems());
-                cli.print_solver_problems(results.solver_problems());
+                cli.print_solver_problems(results.solver_problems(), results.skipped());
+                if results.n_features() > 0 {
+                    cli.print_features(results.features());
+                }
+                cli.print_sampled_options(results.sampled_options());
+                if results.n_minimized() > 0 {
+                    cli.print_minimized(results.minimized());
+                }
                 return ExitCode::from(1);
             }
+            for (slv, cnt) in results.skipped() {
+                cli.info(&format!("skipped {} on {} unsupported instances", slv, cnt));
+            }
             cli.info("no problems found")
         }
+        Exec::Minimize(config, min_config, filter, inst, output) => {
+            cli.info(&format!(
+                "evaluating {}",
+                config.solvers.keys().format(", ")
+            ));
+            let comparison = executor::block_on(eval::compare(
+                inst.clone(),
+                &config.solvers,
+                config.checks,
+                config.pool.clone(),
+            ));
+            let problems = comparison.problems;
+            if problems.is_empty() {
+                cli.info("no problems found, nothing to minimize");
+                return ExitCode::from(0);
+            }
+            let target = match min::Target::select(&problems, &filter) {
+                Some(target) => target,
+                None => {
+                    cli.error("no problem matches the selected target");
+                    return ExitCode::from(1);
+                }
+            };
+            let seed = min_config.seed.unwrap_or_else(rand::random);
+            cli.info(&format!(
+                "minimizing {}: {} (seed {})",
+                config.solvers[&target.solver].label(&target.solver),
+                target.problem,
+                seed
+            ));
+            let inst = executor::block_on(min::minimize(
+                inst,
+                target,
+                &min_config,
+                &config.solvers,
+                config.checks,
+                config.pool,
+                seed,
+            ));
+            inst.to_dimacs_path(&output)
+                .unwrap_or_else(panic_with_err!(&cli));
+            cli.info(&format!("wrote minimized instance to {:?}", output));
+        }
+        Exec::Worker(config, inst) => match eval::evaluate_config(&config, inst) {
+            Ok(pf) => external::write_pareto_front(&mut io::stdout(), &pf)
+                .unwrap_or_else(panic_with_err!(&cli)),
+            // Passed on to the parent process
+            Err(Problem::Panic(info)) => {
+                eprint!(
+                    "{}",
+                    toml::to_string(&info).expect("failed to serialize panic info")
+                );
+                return ExitCode::from(external::PANIC_EXIT_CODE as u8);
+            }
+            Err(Problem::SolverError(err)) => {
+                eprintln!("{}", err);
+                return ExitCode::from(external::SOLVER_ERROR_EXIT_CODE as u8);
+            }
+            Err(prob) => {
+                cli.error(&format!("worker: {}", prob));
+                return ExitCode::from(external::PANIC_EXIT_CODE as u8);
+            }
+        },
         Exec::Evaluate(config, inst) => {
             cli.info(&format!(
                 "evaluating {}",
                 config.solvers.keys().format(", ")
             ));
-            let problems = executor::block_on(eval::compare(inst, &config.solvers, config.pool));
+            let comparison = executor::block_on(eval::compare(
+                inst,
+                &config.solvers,
+                config.checks,
+                config.pool,
+            ));
+            if !comparison.skipped.is_empty() {
+                cli.info(&format!(
+                    "skipped unsupported {}",
+                    comparison.skipped.iter().format(", ")
+                ));
+            }
+            let problems: Vec<_> = comparison
+                .problems
+                .into_iter()
+                .map(|(slv, prob)| (config.solvers[&slv].label(&slv), prob))
+                .collect();
             if !problems.is_empty() {
                 cli.print_problems(&problems);
                 return ExitCode::from(1);
@@ -0,0 +1,396 @@
+//! # Metamorphic Testing
+//!
+//! Transformations of instances that preserve the Pareto front (up to a
+//! simple mapping of the objective values). A solver returning Pareto fronts
+//! that do not correspond for an instance and its transformed copy is buggy,
+//! regardless of what other solvers return.
+
+use std::fmt;
+
+use futures::{channel::mpsc, executor::ThreadPool, StreamExt};
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rustsat::{
+    clause,
+    instances::{ManageVars, MultiOptInstance, Objective, SatInstance},
+    types::{Clause, Lit, RsHashMap, Var},
+};
+use scuttle::types::ParetoFront;
+use serde::Serialize;
+
+use crate::{config::SolverConfig, eval, Problem};
+
+/// The maximum factor to scale objectives with
+const MAX_SCALE: usize = 4;
+
+/// A transformation of an instance that preserves the Pareto front
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum Transformation {
+    /// Renaming all variables according to a random permutation
+    RenameVars,
+    /// Shuffling the order of the clauses
+    ShuffleClauses,
+    /// Shuffling the order of literals in each clause
+    ShuffleLits,
+    /// Permuting the objectives. Objective `i` of the transformed instance is
+    /// objective `perm[i]` of the original instance.
+    PermuteObjs(Vec<usize>),
+    /// Scaling all weights of an objective by a factor. The parameters are
+    /// the objective index and the factor.
+    ScaleObj(usize, usize),
+    /// Splitting all non-unit weight soft clauses into two soft clauses with
+    /// half the weight each
+    SplitSofts,
+}
+
+impl fmt::Display for Transformation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Transformation::RenameVars => write!(f, "rename variables"),
+            Transformation::ShuffleClauses => write!(f, "shuffle clauses"),
+            Transformation::ShuffleLits => write!(f, "shuffle literals"),
+            Transformation::PermuteObjs(perm) => write!(f, "permute objectives {:?}", perm),
+            Transformation::ScaleObj(oidx, factor) => {
+                write!(f, "scale objective {} by {}", oidx, factor)
+            }
+            Transformation::SplitSofts => write!(f, "split soft clauses"),
+        }
+    }
+}
+
+/// An instance in a representation that is easy to transform. Generated
+/// instances do not have objective offsets, offsets are therefore ignored.
+#[derive(Clone)]
+struct Parts {
+    hards: Vec<Clause>,
+    objs: Vec<Vec<(Clause, usize)>>,
+    n_vars: u32,
+}
+
+impl From<MultiOptInstance> for Parts {
+    fn from(value: MultiOptInstance) -> Self {
+        let (cnf, objs, var_manager) = value.as_hard_cls_soft_cls();
+        Parts {
+            hards: cnf.into_iter().collect(),
+            objs: objs
+                .into_iter()
+                .map(|(softs, _)| softs.into_iter().collect())
+                .collect(),
+            n_vars: var_manager.n_used(),
+        }
+    }
+}
+
+impl From<Parts> for MultiOptInstance {
+    fn from(value: Parts) -> Self {
+        let mut constr = SatInstance::default();
+        value.hards.into_iter().for_each(|cl| constr.add_clause(cl));
+        let objs = value
+            .objs
+            .into_iter()
+            .map(|softs| {
+                let mut obj = Objective::default();
+                softs
+                    .into_iter()
+                    .for_each(|(cl, w)| obj.add_soft_clause(w, cl));
+                obj
+            })
+            .collect();
+        MultiOptInstance::compose(constr, objs)
+    }
+}
+
+impl Parts {
+    fn map_lits<F: FnMut(Lit) -> Lit>(&mut self, mut map: F) {
+        let map_cl = |cl: &Clause, map: &mut F| cl.iter().map(|&l| map(l)).collect::<Clause>();
+        for cl in &mut self.hards {
+            *cl = map_cl(cl, &mut map);
+        }
+        for obj in &mut self.objs {
+            for (cl, _) in obj {
+                *cl = map_cl(cl, &mut map);
+            }
+        }
+    }
+
+    fn apply<R: Rng>(&mut self, trans: &Transformation, rng: &mut R) {
+        match trans {
+            Transformation::RenameVars => {
+                let mut perm: Vec<u32> = (0..self.n_vars).collect();
+                perm.shuffle(rng);
+                self.map_lits(|l| Lit::new(perm[l.vidx()], l.is_neg()));
+            }
+            Transformation::ShuffleClauses => {
+                self.hards.shuffle(rng);
+                self.objs.iter_mut().for_each(|obj| obj.shuffle(rng));
+            }
+            Transformation::ShuffleLits => {
+                let shuffle = |cl: &mut Clause, rng: &mut R| {
+                    let mut lits: Vec<Lit> = cl.iter().copied().collect();
+                    lits.shuffle(rng);
+                    *cl = lits.into_iter().collect();
+                };
+                self.hards.iter_mut().for_each(|cl| shuffle(cl, rng));
+                self.objs
+                    .iter_mut()
+                    .for_each(|obj| obj.iter_mut().for_each(|(cl, _)| shuffle(cl, rng)));
+            }
+            Transformation::PermuteObjs(perm) => {
+                let mut objs: Vec<_> = self.objs.drain(..).map(Some).collect();
+                self.objs = perm.iter().map(|&idx| objs[idx].take().unwrap()).collect();
+            }
+            Transformation::ScaleObj(oidx, factor) => {
+                self.objs[*oidx].iter_mut().for_each(|(_, w)| *w *= factor);
+            }
+            Transformation::SplitSofts => {
+                for oidx in 0..self.objs.len() {
+                    let mut split = vec![];
+                    for (cl, w) in &mut self.objs[oidx] {
+                        if *w < 2 {
+                            continue;
+                        }
+                        // Copy the clause with the first literal replaced by
+                        // an equivalent fresh literal, since identical soft
+                        // clauses would be merged
+                        let lit = *cl.iter().next().unwrap();
+                        let eq = Var::new(self.n_vars).pos_lit();
+                        self.n_vars += 1;
+                        self.hards.push(clause![!eq, lit]);
+                        self.hards.push(clause![eq, !lit]);
+                        let copy: Clause =
+                            cl.iter().map(|&l| if l == lit { eq } else { l }).collect();
+                        split.push((copy, *w / 2));
+                        *w -= *w / 2;
+                    }
+                    self.objs[oidx].extend(split);
+                }
+            }
+        }
+    }
+
+    /// Checks whether a sequence of transformations can be applied to this
+    /// instance
+    fn applicable(&self, trans: &[Transformation]) -> bool {
+        trans.iter().all(|t| match t {
+            Transformation::PermuteObjs(perm) => perm.len() == self.objs.len(),
+            Transformation::ScaleObj(oidx, _) => *oidx < self.objs.len(),
+            _ => true,
+        })
+    }
+
+    /// Draws a random non-empty sequence of transformations that can be
+    /// applied to this instance
+    fn random_transformations<R: Rng>(&self, rng: &mut R) -> Vec<Transformation> {
+        loop {
+            let mut trans = vec![];
+            if rng.gen_bool(0.5) {
+                trans.push(Transformation::RenameVars);
+            }
+            if rng.gen_bool(0.5) {
+                trans.push(Transformation::ShuffleClauses);
+            }
+            if rng.gen_bool(0.5) {
+                trans.push(Transformation::ShuffleLits);
+            }
+            if !self.objs.is_empty() && rng.gen_bool(0.5) {
+                let oidx = rng.gen_range(0..self.objs.len());
+                let factor = rng.gen_range(2..=MAX_SCALE);
+                let sum = self.objs[oidx]
+                    .iter()
+                    .try_fold(0usize, |sum, (_, w)| sum.checked_add(*w));
+                if sum
+                    .and_then(|s| s.checked_mul(factor))
+                    .is_some_and(|s| s <= isize::MAX as usize)
+                {
+                    trans.push(Transformation::ScaleObj(oidx, factor));
+                }
+            }
+            if self.objs.len() > 1 && rng.gen_bool(0.5) {
+                let mut perm: Vec<usize> = (0..self.objs.len()).collect();
+                perm.shuffle(rng);
+                trans.push(Transformation::PermuteObjs(perm));
+            }
+            if rng.gen_bool(0.5) {
+                trans.push(Transformation::SplitSofts);
+            }
+            if !trans.is_empty() {
+                return trans;
+            }
+        }
+    }
+}
+
+/// Maps objective values of the transformed instance back to the original
+/// instance. Returns [`None`] if the values cannot come from the transformed
+/// instance.
+fn map_back(mut costs: Vec<isize>, trans: &[Transformation]) -> Option<Vec<isize>> {
+    for t in trans.iter().rev() {
+        match t {
+            Transformation::PermuteObjs(perm) => {
+                let mut orig = vec![0; costs.len()];
+                perm.iter()
+                    .zip(costs.iter())
+                    .for_each(|(&idx, &c)| orig[idx] = c);
+                costs = orig;
+            }
+            Transformation::ScaleObj(oidx, factor) => {
+                let factor = *factor as isize;
+                if costs[*oidx] % factor != 0 {
+                    return None;
+                }
+                costs[*oidx] /= factor;
+            }
+            _ => (),
+        }
+    }
+    Some(costs)
+}
+
+/// Checks whether the Pareto front of a transformed instance corresponds to
+/// the original Pareto front
+fn corresponds(orig: &ParetoFront, transformed: &ParetoFront, trans: &[Transformation]) -> bool {
+    let mut orig: Vec<Vec<isize>> = orig.iter().map(|nd| nd.costs().clone()).collect();
+    let mapped: Option<Vec<Vec<isize>>> = transformed
+        .iter()
+        .map(|nd| map_back(nd.costs().clone(), trans))
+        .collect();
+    let Some(mut mapped) = mapped else {
+        return false;
+    };
+    orig.sort();
+    mapped.sort();
+    orig == mapped
+}
+
+/// Applies a sequence of transformations with a random number generator
+/// seeded by `seed`, so that the same copy can be recreated from the seed
+fn transform(mut parts: Parts, trans: &[Transformation], seed: u64) -> MultiOptInstance {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    trans.iter().for_each(|t| parts.apply(t, &mut rng));
+    parts.into()
+}
+
+/// Recreates the transformed copy of an instance that a problem was found on
+pub fn copy(inst: MultiOptInstance, trans: &[Transformation], seed: u64) -> MultiOptInstance {
+    transform(Parts::from(inst), trans, seed)
+}
+
+/// Checks whether a solver returns a valid Pareto front for a transformed
+/// copy that corresponds to the original Pareto front. Problems of the solver
+/// on the copy itself are wrapped with the transformations and seed, so that
+/// the copy can be recreated to reproduce them.
+fn check_copy(
+    sconf: &SolverConfig,
+    orig: &ParetoFront,
+    copy: MultiOptInstance,
+    trans: &[Transformation],
+    seed: u64,
+) -> Result<(), Problem> {
+    let on_copy = |prob| Problem::OnCopy(Box::new(prob), trans.to_vec(), seed);
+    let pf = eval::evaluate_config(sconf, copy.clone()).map_err(on_copy)?;
+    eval::check_pf(&pf, &copy).map_err(on_copy)?;
+    if !corresponds(orig, &pf, trans) {
+        return Err(Problem::Metamorphic(trans.to_vec(), seed));
+    }
+    Ok(())
+}
+
+/// Reproduces a problem found on a transformed copy by applying a given
+/// sequence of transformations to an instance, with the seed stored in the
+/// problem. Returns the problem on the copy, if the solver has none on the
+/// instance itself.
+pub fn reproduce(
+    inst: MultiOptInstance,
+    sconf: &SolverConfig,
+    trans: &[Transformation],
+    seed: u64,
+) -> Option<Problem> {
+    let parts = Parts::from(inst.clone());
+    if !parts.applicable(trans) || !eval::supported(sconf, &inst) {
+        return None;
+    }
+    let copy = transform(parts, trans, seed);
+    if !eval::supported(sconf, &copy) {
+        return None;
+    }
+    let orig = match eval::evaluate_config(sconf, inst.clone()) {
+        Ok(pf) if eval::check_pf(&pf, &inst).is_ok() => pf,
+        _ => return None,
+    };
+    check_copy(sconf, &orig, copy, trans, seed).err()
+}
+
+/// Runs every solver on `n_copies` randomly transformed copies of an instance
+/// and checks that the Pareto fronts correspond to the fronts the solvers
+/// returned for the original instance in [`eval::compare`]. Solvers without a
+/// valid front for the original instance are skipped, since their problems
+/// are detected by [`eval::compare`], as are copies not supported by a solver.
+pub async fn check(
+    inst: MultiOptInstance,
+    solvers: &RsHashMap<String, SolverConfig>,
+    fronts: &[(String, ParetoFront)],
+    n_copies: u32,
+    seed: u64,
+    pool: Option<ThreadPool>,
+) -> Vec<(String, Problem)> {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let parts = Parts::from(inst.clone());
+    let copies: Vec<(Vec<Transformation>, u64, MultiOptInstance)> = (0..n_copies)
+        .map(|_| {
+            let trans = parts.random_transformations(&mut rng);
+            let seed = rng.gen();
+            let copy = transform(parts.clone(), &trans, seed);
+            (trans, seed, copy)
+        })
+        .collect();
+
+    let (tx_prob, rx_prob) = mpsc::channel::<(String, Problem)>(solvers.len());
+    for (sid, orig) in fronts {
+        let Some(sconf) = solvers.get(sid) else {
+            continue;
+        };
+        if eval::check_pf(orig, &inst).is_err() {
+            continue;
+        }
+        let sid = sid.clone();
+        let sconf = sconf.clone();
+        let orig = orig.clone();
+        let copies = copies.clone();
+        let mut prob_tx = tx_prob.clone();
+        let future_probs = async move {
+            for (trans, seed, copy) in copies {
+                if !eval::supported(&sconf, &copy) {
+                    continue;
+                }
+                if let Err(prob) = check_copy(&sconf, &orig, copy, &trans, seed) {
+                    prob_tx
+                        .try_send((sid, prob))
+                        .expect("failed to send problem");
+                    return;
+                }
+            }
+        };
+        if let Some(ref pool) = pool {
+            pool.spawn_ok(future_probs);
+        } else {
+            future_probs.await;
+        }
+    }
+    drop(tx_prob);
+    rx_prob.collect().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{map_back, Transformation};
+
+    #[test]
+    fn map_back_costs() {
+        let trans = [
+            Transformation::ScaleObj(0, 2),
+            Transformation::PermuteObjs(vec![1, 0]),
+        ];
+        assert_eq!(map_back(vec![3, 8], &trans), Some(vec![4, 3]));
+        assert_eq!(map_back(vec![3, 7], &trans), None);
+    }
+}
@@ -1,12 +1,29 @@
 //! # Minimizing A Faulty Instance
 
-use rustsat::instances::{MultiOptInstance, Objective, SatInstance};
-use scuttle::types::ParetoFront;
+use std::fmt;
 
-use crate::{config::MinimizeConfig, eval, Problem, Solver};
+use std::future::Future;
 
-#[derive(Default, Clone)]
-struct Instance(Vec<Clause>);
+use futures::executor::ThreadPool;
+use rand::{seq::SliceRandom, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rustsat::{
+    instances::{ManageVars, MultiOptInstance, Objective, SatInstance},
+    types::{Lit, RsHashMap, RsHashSet},
+};
+
+use crate::{
+    config::{CheckConfig, MinimizeConfig, SolverConfig},
+    eval,
+    fuzz::Signature,
+    meta, Problem,
+};
+
+#[derive(Clone)]
+struct Instance {
+    cls: Vec<Clause>,
+    n_objs: usize,
+}
 
 #[derive(Clone)]
 struct Clause {
@@ -48,11 +65,13 @@ struct SoftData {
     upper_bound: usize,
 }
 
+#[derive(Clone, Copy)]
 enum Modes {
     MinClauses,
     MinLits,
     MinVars,
     Soft2Hard,
+    RemoveObjs,
     Weight2One,
     WeightBinary,
 }
@@ -60,15 +79,12 @@ enum Modes {
 impl Into<MultiOptInstance> for Instance {
     fn into(self) -> MultiOptInstance {
         let mut constr = SatInstance::default();
-        let mut objs = vec![];
-        self.0.into_iter().for_each(|cl| {
+        let mut objs = vec![Objective::default(); self.n_objs];
+        self.cls.into_iter().for_each(|cl| {
             if !cl.active {
                 return;
             }
             if let Some(soft) = cl.soft {
-                if soft.obj as usize >= objs.len() {
-                    objs.resize((soft.obj + 1).into(), Objective::default());
-                }
                 objs[soft.obj as usize].add_soft_clause(soft.val, cl.cl);
             } else {
                 constr.add_clause(cl.cl);
@@ -81,25 +97,471 @@ impl Into<MultiOptInstance> for Instance {
 impl From<MultiOptInstance> for Instance {
     fn from(value: MultiOptInstance) -> Self {
         let (cnf, objs, _) = value.as_hard_cls_soft_cls();
-        let mut inst = Instance::default();
-        cnf.into_iter().for_each(|cl| inst.0.push(Clause::hard(cl)));
+        let mut inst = Instance {
+            cls: vec![],
+            n_objs: objs.len(),
+        };
+        cnf.into_iter()
+            .for_each(|cl| inst.cls.push(Clause::hard(cl)));
         objs.into_iter().enumerate().for_each(|(idx, obj)| {
             obj.0
                 .into_iter()
-                .for_each(|(cl, w)| inst.0.push(Clause::soft(cl, w, idx.try_into().unwrap())))
+                .for_each(|(cl, w)| inst.cls.push(Clause::soft(cl, w, idx.try_into().unwrap())))
         });
         inst
     }
 }
 
-fn check_instance<S: Solver + From<MultiOptInstance>>(
+impl Instance {
+    /// The indices of active clauses satisfying a predicate
+    fn active<P: Fn(&Clause) -> bool>(&self, pred: P) -> Vec<usize> {
+        self.cls
+            .iter()
+            .enumerate()
+            .filter(|(_, cl)| cl.active && pred(cl))
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    /// Removes literals from a clause, unless this would make it empty
+    fn remove_lits<P: Fn(Lit) -> bool>(&mut self, cidx: usize, remove: P) {
+        let reduced: rustsat::types::Clause = self.cls[cidx]
+            .cl
+            .iter()
+            .copied()
+            .filter(|&l| !remove(l))
+            .collect();
+        if !reduced.is_empty() {
+            self.cls[cidx].cl = reduced;
+        }
+    }
+
+    /// Renames the variables to a dense range starting from zero
+    fn rename_vars(&mut self) {
+        let mut map = RsHashMap::default();
+        for cl in self.cls.iter_mut().filter(|cl| cl.active) {
+            cl.cl = cl
+                .cl
+                .iter()
+                .map(|&l| {
+                    let n_vars = map.len() as u32;
+                    let idx = *map.entry(l.var()).or_insert(n_vars);
+                    Lit::new(idx, l.is_neg())
+                })
+                .collect();
+        }
+    }
+
+    /// Removes an objective by deactivating its soft clauses and shifting
+    /// later objectives
+    fn remove_obj(&mut self, oidx: u8) {
+        for cl in &mut self.cls {
+            if let Some(soft) = &mut cl.soft {
+                if soft.obj == oidx {
+                    cl.active = false;
+                } else if soft.obj > oidx {
+                    soft.obj -= 1;
+                }
+            }
+        }
+        self.n_objs -= 1;
+    }
+}
+
+/// The problem that the minimizer keeps reproducible
+pub struct Target {
+    pub solver: String,
+    pub problem: Problem,
+}
+
+/// Restricts which problem is selected as the minimization target
+#[derive(Debug, Clone, Default)]
+pub struct TargetFilter {
+    /// Only select problems of this solver
+    pub solver: Option<String>,
+    /// Only select problems of this kind
+    pub kind: Option<String>,
+}
+
+impl TargetFilter {
+    fn matches(&self, solver: &str, problem: &Problem) -> bool {
+        self.solver.as_ref().map_or(true, |s| s == solver)
+            && self.kind.as_ref().map_or(true, |k| k == problem.kind())
+    }
+}
+
+impl Target {
+    /// Selects the problem to minimize for from the problems found on an
+    /// instance. This is the first problem passing the filter when ordered by
+    /// solver name.
+    pub fn select(problems: &[(String, Problem)], filter: &TargetFilter) -> Option<Target> {
+        problems
+            .iter()
+            .filter(|(solver, problem)| filter.matches(solver, problem))
+            .min_by(|a, b| a.0.cmp(&b.0))
+            .map(|(solver, problem)| Target {
+                solver: solver.clone(),
+                problem: problem.clone(),
+            })
+    }
+}
+
+/// The size of an instance, ordered by the number of variables first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Size {
+    pub n_vars: u32,
+    pub n_clauses: usize,
+}
+
+impl From<&MultiOptInstance> for Size {
+    fn from(value: &MultiOptInstance) -> Self {
+        let (cnf, objs, var_manager) = value.clone().as_hard_cls_soft_cls();
+        Size {
+            n_vars: var_manager.n_used(),
+            n_clauses: objs
+                .iter()
+                .fold(cnf.len(), |cnt, (softs, _)| cnt + softs.len()),
+        }
+    }
+}
+
+impl fmt::Display for Size {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} vars, {} clauses", self.n_vars, self.n_clauses)
+    }
+}
+
+struct Minimizer<'a> {
     inst: Instance,
-) -> Result<ParetoFront, Problem> {
-    let inst: MultiOptInstance = inst.into();
-    eval::evaluate::<S>(inst)
+    target: Target,
+    solvers: &'a RsHashMap<String, SolverConfig>,
+    single: RsHashMap<String, SolverConfig>,
+    checks: CheckConfig,
+    pool: Option<ThreadPool>,
+}
+
+/// Checks whether the target problem is still reproducible on an instance,
+/// i.e., whether the target solver has a problem with the same signature.
+/// Problems found by comparing to other solvers are checked with all solvers,
+/// other problems only with the target solver.
+async fn check_instance(inst: &Instance, min: &Minimizer<'_>) -> bool {
+    let inst: MultiOptInstance = inst.clone().into();
+    let target = Signature::new(min.target.solver.clone(), &min.target.problem);
+    let matches = |prob: &Problem| Signature::new(min.target.solver.clone(), prob) == target;
+    if let Some((trans, seed)) = min.target.problem.transformations() {
+        return meta::reproduce(inst, &min.single[&min.target.solver], trans, seed)
+            .is_some_and(|prob| matches(&prob));
+    }
+    let solvers = if min.target.problem.is_comparison() {
+        min.solvers
+    } else {
+        &min.single
+    };
+    eval::compare(inst, solvers, min.checks, min.pool.clone())
+        .await
+        .problems
+        .iter()
+        .any(|(slv, prob)| slv == &min.target.solver && matches(prob))
+}
+
+/// Delta debugging over a set of units. `apply` reduces the instance for a
+/// subset of the units and `check` decides whether a candidate still shows
+/// the problem. Returns whether the instance was reduced.
+async fn ddmin<U, F, C, Fut>(inst: &mut Instance, mut units: Vec<U>, apply: F, mut check: C) -> bool
+where
+    U: Clone,
+    F: Fn(&mut Instance, &[U]),
+    C: FnMut(Instance) -> Fut,
+    Fut: Future<Output = bool>,
+{
+    let mut reduced = false;
+    let mut n_chunks = 2;
+    while !units.is_empty() {
+        let chunk_size = (units.len() + n_chunks - 1) / n_chunks;
+        let mut success = None;
+        for (cidx, chunk) in units.chunks(chunk_size).enumerate() {
+            let mut cand = inst.clone();
+            apply(&mut cand, chunk);
+            if check(cand.clone()).await {
+                *inst = cand;
+                success = Some(cidx);
+                break;
+            }
+        }
+        match success {
+            Some(cidx) => {
+                reduced = true;
+                let end = std::cmp::min((cidx + 1) * chunk_size, units.len());
+                units.drain(cidx * chunk_size..end);
+                n_chunks = std::cmp::max(n_chunks - 1, 2);
+            }
+            None => {
+                if chunk_size == 1 {
+                    break;
+                }
+                n_chunks = std::cmp::min(2 * n_chunks, units.len());
+            }
+        }
+    }
+    reduced
 }
 
-pub fn minimize(inst: MultiOptInstance, config: MinimizeConfig) -> MultiOptInstance {
-    let inst: Instance = inst.into();
-    todo!()
+impl Minimizer<'_> {
+    /// Delta debugging over a set of units, checking candidates against the
+    /// target problem
+    async fn ddmin<U, F>(&mut self, units: Vec<U>, apply: F) -> bool
+    where
+        U: Clone,
+        F: Fn(&mut Instance, &[U]),
+    {
+        let mut inst = self.inst.clone();
+        let this: &Self = self;
+        let reduced = ddmin(&mut inst, units, apply, |cand| async move {
+            check_instance(&cand, this).await
+        })
+        .await;
+        self.inst = inst;
+        reduced
+    }
+
+    /// Binary searches the smallest weight for each soft clause
+    async fn weight_binary_search(&mut self) -> bool {
+        let mut reduced = false;
+        for cidx in self.inst.active(|cl| cl.soft.is_some_and(|s| s.val > 1)) {
+            let soft = self.inst.cls[cidx].soft.as_mut().unwrap();
+            soft.lower_bound = 0;
+            soft.upper_bound = soft.val;
+            loop {
+                let soft = self.inst.cls[cidx].soft.as_mut().unwrap();
+                if soft.upper_bound - soft.lower_bound <= 1 {
+                    break;
+                }
+                soft.previous = soft.val;
+                soft.val = soft.lower_bound + (soft.upper_bound - soft.lower_bound) / 2;
+                let holds = check_instance(&self.inst, self).await;
+                let soft = self.inst.cls[cidx].soft.as_mut().unwrap();
+                if holds {
+                    soft.upper_bound = soft.val;
+                    reduced = true;
+                } else {
+                    soft.lower_bound = soft.val;
+                    soft.val = soft.previous;
+                }
+            }
+        }
+        reduced
+    }
+
+    async fn run_mode(&mut self, mode: Modes) -> bool {
+        match mode {
+            Modes::MinClauses => {
+                let units = self.inst.active(|_| true);
+                self.ddmin(units, |inst, chunk| {
+                    chunk.iter().for_each(|&cidx| inst.cls[cidx].active = false)
+                })
+                .await
+            }
+            Modes::MinLits => {
+                let units: Vec<(usize, Lit)> = self
+                    .inst
+                    .active(|cl| cl.cl.len() > 1)
+                    .into_iter()
+                    .flat_map(|cidx| {
+                        self.inst.cls[cidx]
+                            .cl
+                            .iter()
+                            .map(move |&l| (cidx, l))
+                            .collect::<Vec<_>>()
+                    })
+                    .collect();
+                self.ddmin(units, |inst, chunk| {
+                    chunk
+                        .iter()
+                        .for_each(|&(cidx, lit)| inst.remove_lits(cidx, |l| l == lit))
+                })
+                .await
+            }
+            Modes::MinVars => {
+                let vars: RsHashSet<_> = self
+                    .inst
+                    .cls
+                    .iter()
+                    .filter(|cl| cl.active)
+                    .flat_map(|cl| cl.cl.iter().map(|l| l.var()))
+                    .collect();
+                let mut units: Vec<_> = vars.into_iter().collect();
+                units.sort();
+                self.ddmin(units, |inst, chunk| {
+                    for cidx in inst.active(|_| true) {
+                        inst.remove_lits(cidx, |l| chunk.contains(&l.var()));
+                    }
+                })
+                .await
+            }
+            Modes::Soft2Hard => {
+                let units = self.inst.active(|cl| cl.soft.is_some());
+                self.ddmin(units, |inst, chunk| {
+                    chunk.iter().for_each(|&cidx| inst.cls[cidx].soft = None)
+                })
+                .await
+            }
+            Modes::RemoveObjs => {
+                let mut reduced = false;
+                for oidx in (0..self.inst.n_objs).rev() {
+                    let mut cand = self.inst.clone();
+                    cand.remove_obj(oidx as u8);
+                    if check_instance(&cand, self).await {
+                        self.inst = cand;
+                        reduced = true;
+                    }
+                }
+                reduced
+            }
+            Modes::Weight2One => {
+                let units = self.inst.active(|cl| cl.soft.is_some_and(|s| s.val > 1));
+                self.ddmin(units, |inst, chunk| {
+                    chunk
+                        .iter()
+                        .for_each(|&cidx| inst.cls[cidx].soft.as_mut().unwrap().val = 1)
+                })
+                .await
+            }
+            Modes::WeightBinary => self.weight_binary_search().await,
+        }
+    }
+
+    /// Applies a transformation that does not reduce the instance, if the
+    /// problem remains reproducible
+    async fn try_transform<F: FnOnce(&mut Instance)>(&mut self, transform: F) {
+        let mut cand = self.inst.clone();
+        transform(&mut cand);
+        if check_instance(&cand, self).await {
+            self.inst = cand;
+        }
+    }
+}
+
+/// Minimizes an instance while keeping the target problem reproducible. The
+/// seed is used for shuffling.
+pub async fn minimize(
+    inst: MultiOptInstance,
+    target: Target,
+    config: &MinimizeConfig,
+    solvers: &RsHashMap<String, SolverConfig>,
+    checks: CheckConfig,
+    pool: Option<ThreadPool>,
+    seed: u64,
+) -> MultiOptInstance {
+    let mut single = RsHashMap::default();
+    single.insert(target.solver.clone(), solvers[&target.solver].clone());
+    let mut min = Minimizer {
+        inst: inst.into(),
+        target,
+        solvers,
+        single,
+        checks,
+        pool,
+    };
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let modes: Vec<Modes> = [
+        (config.remove_objectives, Modes::RemoveObjs),
+        (config.min_clauses, Modes::MinClauses),
+        (config.soft_to_hard, Modes::Soft2Hard),
+        (config.min_variables, Modes::MinVars),
+        (config.min_literals, Modes::MinLits),
+        (config.weight_to_one, Modes::Weight2One),
+        (config.weight_binary_search, Modes::WeightBinary),
+    ]
+    .into_iter()
+    .filter_map(|(enabled, mode)| enabled.unwrap_or(true).then_some(mode))
+    .collect();
+    for _ in 0..config.max_rounds {
+        if config.shuffle_clauses.unwrap_or(false) {
+            let mut cls = min.inst.cls.clone();
+            cls.shuffle(&mut rng);
+            min.try_transform(|inst| inst.cls = cls).await;
+        }
+        if config.shuffle_literals.unwrap_or(false) {
+            let mut cls = min.inst.cls.clone();
+            for cl in &mut cls {
+                let mut lits: Vec<Lit> = cl.cl.iter().copied().collect();
+                lits.shuffle(&mut rng);
+                cl.cl = lits.into_iter().collect();
+            }
+            min.try_transform(|inst| inst.cls = cls).await;
+        }
+        let mut reduced = false;
+        for &mode in &modes {
+            reduced |= min.run_mode(mode).await;
+        }
+        if !reduced {
+            break;
+        }
+    }
+    if config.rename_variable.unwrap_or(true) {
+        min.try_transform(Instance::rename_vars).await;
+    }
+    min.inst.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use rustsat::types::Lit;
+
+    use super::{Clause, Instance};
+
+    /// An instance of hard unit clauses, clause `i` containing variable `i`
+    fn units(n: u32) -> Instance {
+        Instance {
+            cls: (0..n)
+                .map(|idx| Clause::hard([Lit::positive(idx)].into_iter().collect()))
+                .collect(),
+            n_objs: 0,
+        }
+    }
+
+    fn contains(inst: &Instance, idx: u32) -> bool {
+        inst.cls
+            .iter()
+            .any(|cl| cl.active && cl.cl.iter().any(|&l| l == Lit::positive(idx)))
+    }
+
+    fn remaining(inst: &Instance) -> Vec<usize> {
+        inst.active(|_| true)
+    }
+
+    fn ddmin<P: Fn(&Instance) -> bool>(inst: &mut Instance, pred: P) -> bool {
+        let units = inst.active(|_| true);
+        block_on(super::ddmin(
+            inst,
+            units,
+            |inst, chunk| chunk.iter().for_each(|&cidx| inst.cls[cidx].active = false),
+            |cand| {
+                let holds = pred(&cand);
+                async move { holds }
+            },
+        ))
+    }
+
+    #[test]
+    fn ddmin_single_clause() {
+        let mut inst = units(10);
+        assert!(ddmin(&mut inst, |inst| contains(inst, 3)));
+        assert_eq!(remaining(&inst), vec![3]);
+    }
+
+    #[test]
+    fn ddmin_two_clauses() {
+        let mut inst = units(10);
+        assert!(ddmin(&mut inst, |inst| contains(inst, 2) && contains(inst, 7)));
+        assert_eq!(remaining(&inst), vec![2, 7]);
+    }
+
+    #[test]
+    fn ddmin_not_reproducible() {
+        let mut inst = units(10);
+        assert!(!ddmin(&mut inst, |_| false));
+        assert_eq!(remaining(&inst), (0..10).collect::<Vec<_>>());
+    }
 }
@@ -1,92 +1,317 @@
 //! # Scuttle Configs
 
-use rustsat::{encodings::pb::DbGte, instances::MultiOptInstance, types::Lit};
-use scuttle::{types::ParetoFront, KernelFunctions, Solve};
+use rand::Rng;
+use rustsat::{
+    encodings::{card::Totalizer, pb::DbGte},
+    instances::{BasicVarManager, MultiOptInstance},
+    solvers::{DefIncSolver, PhaseLit, SolveIncremental, SolveStats},
+    types::{Assignment, Clause, Lit},
+};
+use scuttle::{
+    options::{
+        BuildEncodings, DivConAnchor, DivConOptions, EnumOptions, HeurImprWhen, KernelOptions,
+        SubProblemSize,
+    },
+    types::ParetoFront,
+    KernelFunctions, Solve,
+};
 
-use crate::Solver;
+use crate::{
+    config::{
+        BuildEncodingsConfig, EnumConfig, HeurImprConfig, ScuttleConfig, ScuttleOptions,
+        SubProblemSizeConfig,
+    },
+    Solver,
+};
 
-pub struct PMin(scuttle::PMinimal);
+impl From<EnumConfig> for EnumOptions {
+    fn from(value: EnumConfig) -> Self {
+        match value {
+            EnumConfig::NoEnum => EnumOptions::NoEnum,
+            EnumConfig::Solutions { limit } => EnumOptions::Solutions(limit),
+            EnumConfig::PMCSs { limit } => EnumOptions::PMCSs(limit),
+        }
+    }
+}
+
+impl From<HeurImprConfig> for HeurImprWhen {
+    fn from(value: HeurImprConfig) -> Self {
+        match value {
+            HeurImprConfig::Never => HeurImprWhen::Never,
+            HeurImprConfig::Always => HeurImprWhen::Always,
+            HeurImprConfig::OuterLoop => HeurImprWhen::OuterLoop,
+        }
+    }
+}
+
+impl From<BuildEncodingsConfig> for BuildEncodings {
+    fn from(value: BuildEncodingsConfig) -> Self {
+        match value {
+            BuildEncodingsConfig::Once => BuildEncodings::Once,
+            BuildEncodingsConfig::Rebuild => BuildEncodings::Rebuild,
+            BuildEncodingsConfig::CleanRebuild => BuildEncodings::CleanRebuild,
+        }
+    }
+}
+
+impl From<SubProblemSizeConfig> for SubProblemSize {
+    fn from(value: SubProblemSizeConfig) -> Self {
+        match value {
+            SubProblemSizeConfig::Abs(size) => SubProblemSize::Abs(size),
+            SubProblemSizeConfig::Smaller(size) => SubProblemSize::Smaller(size),
+        }
+    }
+}
+
+/// Randomly samples all options that are not set and that the algorithm takes
+pub fn randomize_options<R: Rng>(alg: &ScuttleConfig, opts: &mut ScuttleOptions, rng: &mut R) {
+    let limit = |rng: &mut R| rng.gen_bool(0.5).then(|| rng.gen_range(1..=3));
+    opts.enumeration
+        .get_or_insert_with(|| match rng.gen_range(0..3) {
+            0 => EnumConfig::NoEnum,
+            1 => EnumConfig::Solutions { limit: limit(rng) },
+            _ => EnumConfig::PMCSs { limit: limit(rng) },
+        });
+    opts.solution_tightening
+        .get_or_insert_with(|| match rng.gen_range(0..3) {
+            0 => HeurImprConfig::Never,
+            1 => HeurImprConfig::Always,
+            _ => HeurImprConfig::OuterLoop,
+        });
+    for opt in [
+        &mut opts.solution_guided_search,
+        &mut opts.reserve_enc_vars,
+        &mut opts.store_cnf,
+        &mut opts.core_trimming,
+        &mut opts.core_minimization,
+        &mut opts.core_exhaustion,
+    ] {
+        opt.get_or_insert_with(|| rng.gen_bool(0.5));
+    }
+    if !alg.is_divcon() {
+        return;
+    }
+    opts.build_encodings
+        .get_or_insert_with(|| match rng.gen_range(0..3) {
+            0 => BuildEncodingsConfig::Once,
+            1 => BuildEncodingsConfig::Rebuild,
+            _ => BuildEncodingsConfig::CleanRebuild,
+        });
+    for opt in [
+        &mut opts.rebase_encodings,
+        &mut opts.reset_after_global_ideal,
+    ] {
+        opt.get_or_insert_with(|| rng.gen_bool(0.5));
+    }
+    if alg.takes_sub_problem_size() {
+        opts.sub_problem_size.get_or_insert_with(|| {
+            if rng.gen_bool(0.5) {
+                SubProblemSizeConfig::Smaller(0)
+            } else {
+                SubProblemSizeConfig::Abs(1)
+            }
+        });
+    }
+    if alg.takes_n_minus() {
+        opts.n_minus.get_or_insert_with(|| rng.gen_range(1..=2));
+    }
+}
 
-impl From<MultiOptInstance> for PMin {
-    fn from(value: MultiOptInstance) -> Self {
-        let opts = scuttle::options::KernelOptions::default();
-        PMin(scuttle::PMinimal::new_defaults(value, opts).expect("scuttle error"))
+fn kernel_opts(value: &ScuttleOptions) -> KernelOptions {
+    let mut opts = KernelOptions::default();
+    if let Some(val) = value.enumeration {
+        opts.enumeration = val.into();
+    }
+    if let Some(val) = value.solution_guided_search {
+        opts.solution_guided_search = val;
+    }
+    if let Some(val) = value.solution_tightening {
+        opts.heuristic_improvements.solution_tightening = val.into();
+    }
+    if let Some(val) = value.reserve_enc_vars {
+        opts.reserve_enc_vars = val;
+    }
+    if let Some(val) = value.store_cnf {
+        opts.store_cnf = val;
+    }
+    if let Some(val) = value.core_trimming {
+        opts.core_trimming = val;
     }
+    if let Some(val) = value.core_minimization {
+        opts.core_minimization = val;
+    }
+    if let Some(val) = value.core_exhaustion {
+        opts.core_exhaustion = val;
+    }
+    opts
 }
 
-impl Solver for PMin {
-    fn run(&mut self) -> ParetoFront {
+fn divcon_opts(value: &ScuttleOptions, anchor: DivConAnchor) -> DivConOptions {
+    let mut opts = DivConOptions {
+        kernel: kernel_opts(value),
+        anchor,
+        ..Default::default()
+    };
+    if let Some(val) = value.build_encodings {
+        opts.build_encodings = val.into();
+    }
+    if let Some(val) = value.rebase_encodings {
+        opts.rebase_encodings = val;
+    }
+    if let Some(val) = value.reset_after_global_ideal {
+        opts.reset_after_global_ideal = val;
+    }
+    if let Some(val) = &value.inprocessing {
+        opts.inpro = Some(val.clone());
+    }
+    opts
+}
+
+/// SAT oracles that the scuttle solvers can run on
+pub trait Oracle: SolveIncremental + SolveStats + PhaseLit + Default + 'static {}
+
+impl<O> Oracle for O where O: SolveIncremental + SolveStats + PhaseLit + Default + 'static {}
+
+/// The blocking clause generator type of the scuttle solvers
+type Bcg = fn(Assignment) -> Clause;
+
+pub struct PMin<PBE = DbGte, O = DefIncSolver<'static, 'static>>(
+    scuttle::PMinimal<PBE, Totalizer, BasicVarManager, Bcg, O>,
+);
+
+impl<PBE, O> PMin<PBE, O>
+where
+    PBE: rustsat::encodings::pb::BoundUpperIncremental + FromIterator<(Lit, usize)>,
+    O: Oracle,
+{
+    pub fn new(inst: MultiOptInstance, opts: &ScuttleOptions) -> Result<Self, String> {
+        scuttle::PMinimal::new_defaults(inst, kernel_opts(opts))
+            .map(PMin)
+            .map_err(|err| err.to_string())
+    }
+}
+
+impl<PBE, O> Solver for PMin<PBE, O>
+where
+    PBE: rustsat::encodings::pb::BoundUpperIncremental,
+    O: Oracle,
+{
+    fn run(&mut self) -> Result<ParetoFront, String> {
         self.0
             .solve(scuttle::Limits::none())
-            .expect("scuttle error");
-        self.0.pareto_front()
+            .map_err(|err| err.to_string())?;
+        Ok(self.0.pareto_front())
     }
 }
 
-pub struct PMinCoreBoosting(scuttle::solver::divcon::SeqDivCon);
-
-impl From<MultiOptInstance> for PMinCoreBoosting {
-    fn from(value: MultiOptInstance) -> Self {
-        let inst = value.clone();
-        let opts = scuttle::options::DivConOptions {
-            anchor: scuttle::options::DivConAnchor::PMinimal(
-                scuttle::options::SubProblemSize::Smaller(0),
-            ),
-            ..Default::default()
-        };
-        PMinCoreBoosting(
-            scuttle::solver::divcon::SeqDivCon::new_defaults(value, opts).expect("scuttle error"),
+pub struct DivCon<O = DefIncSolver<'static, 'static>>(scuttle::solver::divcon::SeqDivCon<O>);
+
+impl<O: Oracle> DivCon<O> {
+    pub fn new(
+        inst: MultiOptInstance,
+        opts: &ScuttleOptions,
+        anchor: DivConAnchor,
+    ) -> Result<Self, String> {
+        let opts = divcon_opts(opts, anchor);
+        scuttle::solver::divcon::SeqDivCon::new_defaults(inst, opts)
+            .map(DivCon)
+            .map_err(|err| err.to_string())
+    }
+
+    /// Core-boosted p-minimal, i.e., the p-minimal anchor on the full problem
+    pub fn core_boosted_pmin(
+        inst: MultiOptInstance,
+        opts: &ScuttleOptions,
+    ) -> Result<Self, String> {
+        DivCon::new(
+            inst,
+            opts,
+            DivConAnchor::PMinimal(SubProblemSize::Smaller(0)),
         )
     }
+
+    pub fn pmin(inst: MultiOptInstance, opts: &ScuttleOptions) -> Result<Self, String> {
+        let size = opts
+            .sub_problem_size
+            .map_or(SubProblemSize::Smaller(0), Into::into);
+        DivCon::new(inst, opts, DivConAnchor::PMinimal(size))
+    }
+
+    pub fn lower_bounding(inst: MultiOptInstance, opts: &ScuttleOptions) -> Result<Self, String> {
+        let size = opts
+            .sub_problem_size
+            .map_or(SubProblemSize::Smaller(0), Into::into);
+        DivCon::new(inst, opts, DivConAnchor::LowerBounding(size))
+    }
+
+    pub fn n_minus(inst: MultiOptInstance, opts: &ScuttleOptions) -> Result<Self, String> {
+        DivCon::new(inst, opts, DivConAnchor::NMinus(opts.n_minus.unwrap_or(1)))
+    }
 }
 
-impl Solver for PMinCoreBoosting {
-    fn run(&mut self) -> ParetoFront {
+impl<O: Oracle> Solver for DivCon<O> {
+    fn run(&mut self) -> Result<ParetoFront, String> {
         self.0
             .solve(scuttle::Limits::none())
-            .expect("scuttle error");
-        self.0.pareto_front()
+            .map_err(|err| err.to_string())?;
+        Ok(self.0.pareto_front())
     }
 }
 
-pub struct BiOptSat<PBE = DbGte>(scuttle::BiOptSat<PBE>);
+pub struct BiOptSat<PBE = DbGte, O = DefIncSolver<'static, 'static>>(
+    scuttle::BiOptSat<PBE, Totalizer, BasicVarManager, Bcg, O>,
+);
 
-impl<PBE> From<MultiOptInstance> for BiOptSat<PBE>
+impl<PBE, O> BiOptSat<PBE, O>
 where
     PBE: rustsat::encodings::pb::BoundUpperIncremental + FromIterator<(Lit, usize)>,
+    O: Oracle,
 {
-    fn from(value: MultiOptInstance) -> Self {
-        let opts = scuttle::options::KernelOptions::default();
-        BiOptSat(scuttle::BiOptSat::<PBE>::new_defaults(value, opts).expect("scuttle error"))
+    pub fn new(inst: MultiOptInstance, opts: &ScuttleOptions) -> Result<Self, String> {
+        scuttle::BiOptSat::new_defaults(inst, kernel_opts(opts))
+            .map(BiOptSat)
+            .map_err(|err| err.to_string())
     }
 }
 
-impl<PBE> Solver for BiOptSat<PBE>
+impl<PBE, O> Solver for BiOptSat<PBE, O>
 where
     PBE: rustsat::encodings::pb::BoundUpperIncremental,
+    O: Oracle,
 {
-    fn run(&mut self) -> ParetoFront {
+    fn run(&mut self) -> Result<ParetoFront, String> {
         self.0
             .solve(scuttle::Limits::none())
-            .expect("scuttle error");
-        self.0.pareto_front()
+            .map_err(|err| err.to_string())?;
+        Ok(self.0.pareto_front())
     }
 }
 
-pub struct LowerBounding(scuttle::LowerBounding);
+pub struct LowerBounding<PBE = DbGte, O = DefIncSolver<'static, 'static>>(
+    scuttle::LowerBounding<PBE, Totalizer, BasicVarManager, Bcg, O>,
+);
 
-impl From<MultiOptInstance> for LowerBounding {
-    fn from(value: MultiOptInstance) -> Self {
-        let opts = scuttle::options::KernelOptions::default();
-        LowerBounding(scuttle::LowerBounding::new_defaults(value, opts).expect("scuttle error"))
+impl<PBE, O> LowerBounding<PBE, O>
+where
+    PBE: rustsat::encodings::pb::BoundUpperIncremental + FromIterator<(Lit, usize)>,
+    O: Oracle,
+{
+    pub fn new(inst: MultiOptInstance, opts: &ScuttleOptions) -> Result<Self, String> {
+        scuttle::LowerBounding::new_defaults(inst, kernel_opts(opts))
+            .map(LowerBounding)
+            .map_err(|err| err.to_string())
     }
 }
 
-impl Solver for LowerBounding {
-    fn run(&mut self) -> ParetoFront {
+impl<PBE, O> Solver for LowerBounding<PBE, O>
+where
+    PBE: rustsat::encodings::pb::BoundUpperIncremental,
+    O: Oracle,
+{
+    fn run(&mut self) -> Result<ParetoFront, String> {
         self.0
             .solve(scuttle::Limits::none())
-            .expect("scuttle error");
-        self.0.pareto_front()
+            .map_err(|err| err.to_string())?;
+        Ok(self.0.pareto_front())
     }
 }
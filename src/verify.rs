@@ -0,0 +1,158 @@
+//! # Independent Verification Of Pareto Fronts
+//!
+//! Checks that are performed with an independent SAT solver rather than by
+//! comparing to the results of other solvers.
+
+use rustsat::{
+    clause,
+    encodings::pb::{BoundUpper, DbGte},
+    instances::{Cnf, ManageVars, MultiOptInstance},
+    solvers::{DefIncSolver, Solve, SolverResult},
+    types::{Assignment, Clause, Lit, Var},
+};
+use scuttle::types::ParetoFront;
+
+use crate::Problem;
+
+/// A solution proving that a solver's result is wrong
+#[derive(Debug, Clone)]
+pub struct Witness {
+    pub costs: Vec<isize>,
+    pub solution: Assignment,
+}
+
+/// Checks whether a Pareto front is complete, i.e., that there is no solution
+/// that is not weakly dominated by any of the returned non-dominated points.
+/// Assumes that the Pareto front has already been checked with
+/// [`crate::eval::check_pf`].
+pub fn check_complete(pf: &ParetoFront, inst: &MultiOptInstance) -> Result<(), Problem> {
+    let (mut cnf, objs, mut var_manager) = inst.clone().as_hard_cls_soft_cls();
+    // Build an upper bounding encoding for each objective
+    let mut encs = Vec::with_capacity(objs.len());
+    for (softs, offset) in objs {
+        let mut lits: Vec<(Lit, usize)> = vec![];
+        for (mut cl, w) in softs {
+            if cl.len() == 1 {
+                lits.push((!*cl.iter().next().unwrap(), w));
+                continue;
+            }
+            let blit = var_manager.new_var().pos_lit();
+            cl.add(blit);
+            cnf.add_clause(cl);
+            lits.push((blit, w));
+        }
+        encs.push((DbGte::from_iter(lits), offset));
+    }
+    // Require not being weakly dominated by any returned point
+    for ndom in pf.iter() {
+        let mut cl = Clause::new();
+        for ((enc, offset), &cost) in encs.iter_mut().zip(ndom.costs()) {
+            let ub = cost - 1 - *offset;
+            if ub < 0 {
+                continue;
+            }
+            let ub = ub as usize;
+            cnf.extend(enc.encode_ub(ub..=ub, &mut var_manager));
+            let Ok(assumps) = enc.enforce_ub(ub) else {
+                continue;
+            };
+            let ind = var_manager.new_var().pos_lit();
+            assumps
+                .into_iter()
+                .for_each(|a| cnf.add_clause(clause![!ind, a]));
+            cl.add(ind);
+        }
+        if cl.is_empty() {
+            // Every solution is weakly dominated by this point
+            return Ok(());
+        }
+        cnf.add_clause(cl);
+    }
+    match solve(cnf, var_manager.max_var()) {
+        None => Ok(()),
+        Some(solution) => Err(Problem::Incomplete(witness(solution, inst))),
+    }
+}
+
+/// Checks that the hard clauses of an instance are unsatisfiable, for
+/// verifying an empty Pareto front
+pub fn check_unsat(inst: &MultiOptInstance) -> Result<(), Problem> {
+    let (cnf, var_manager) = inst.clone().decompose().0.as_cnf();
+    match solve(cnf, var_manager.max_var()) {
+        None => Ok(()),
+        Some(solution) => Err(Problem::WrongUnsat(witness(solution, inst))),
+    }
+}
+
+/// Solves a CNF with an independent SAT solver and returns a solution, if
+/// there is one
+fn solve(cnf: Cnf, max_var: Option<Var>) -> Option<Assignment> {
+    let mut solver = DefIncSolver::default();
+    solver
+        .add_cnf(cnf)
+        .expect("failed to add clauses to verification oracle");
+    match solver.solve().expect("verification oracle error") {
+        SolverResult::Sat => Some(
+            solver
+                .solution(max_var.unwrap_or(Var::new(0)))
+                .expect("failed to get solution from verification oracle"),
+        ),
+        SolverResult::Unsat => None,
+        SolverResult::Interrupted => panic!("verification oracle interrupted"),
+    }
+}
+
+fn witness(solution: Assignment, inst: &MultiOptInstance) -> Witness {
+    let costs = inst
+        .cost(&solution)
+        .expect("verification witness is not a solution");
+    Witness { costs, solution }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustsat::{
+        clause,
+        instances::{MultiOptInstance, Objective, SatInstance},
+        lit,
+    };
+    use scuttle::types::{NonDomPoint, ParetoFront};
+
+    use crate::Problem;
+
+    fn instance() -> MultiOptInstance {
+        let mut constr = SatInstance::default();
+        constr.add_clause(clause![lit![0], lit![1]]);
+        let mut obj1 = Objective::default();
+        obj1.add_soft_clause(1, clause![!lit![0]]);
+        let mut obj2 = Objective::default();
+        obj2.add_soft_clause(1, clause![!lit![1]]);
+        MultiOptInstance::compose(constr, vec![obj1, obj2])
+    }
+
+    #[test]
+    fn complete() {
+        let mut pf = ParetoFront::default();
+        pf.add_nd(NonDomPoint::new(vec![1, 0]));
+        pf.add_nd(NonDomPoint::new(vec![0, 1]));
+        assert!(super::check_complete(&pf, &instance()).is_ok());
+    }
+
+    #[test]
+    fn wrong_unsat() {
+        match super::check_unsat(&instance()) {
+            Err(Problem::WrongUnsat(_)) => (),
+            _ => panic!("expected wrong unsat"),
+        }
+    }
+
+    #[test]
+    fn incomplete() {
+        let mut pf = ParetoFront::default();
+        pf.add_nd(NonDomPoint::new(vec![1, 0]));
+        match super::check_complete(&pf, &instance()) {
+            Err(Problem::Incomplete(wit)) => assert_eq!(wit.costs, vec![0, 1]),
+            _ => panic!("expected incomplete pareto front"),
+        }
+    }
+}